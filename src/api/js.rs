@@ -0,0 +1,527 @@
+use crate::api::{
+    ControllerState, CreepState, GameApi, HostileCreepState, MineralState, Owner, PileState,
+    SiteState, SourceState, SpawnState, SpawningCreep, Store, StructureState,
+};
+use crate::tasks::PileKind;
+use screeps::memory::MemoryReference;
+use screeps::pathfinder::{self, CostMatrix, LocalCostMatrix, SearchOptions};
+use screeps::{
+    find, Attackable, CanStoreEnergy, ConstructionSite, Direction, HasId, HasPosition, HasStore,
+    Mineral, Part, Position, Resource, ResourceType, ReturnCode, RoomName, RoomObjectProperties,
+    SizedRoomObject, Source, SpawnOptions, Structure, StructureController, StructureProperties,
+    StructureSpawn, Terrain, Tombstone,
+};
+use std::collections::HashMap;
+use stdweb::js;
+use stdweb::unstable::TryInto;
+
+/// The live game, reached through `screeps::game` and `js!`.
+pub struct JsApi;
+
+fn creep_state(creep: &screeps::Creep) -> CreepState {
+    let spawning = creep.spawning();
+    CreepState {
+        name: creep.name(),
+        pos: creep.pos(),
+        spawning,
+        carry_total: creep.carry_total(),
+        carry_capacity: creep.carry_capacity(),
+        energy: creep.energy(),
+        fatigue: creep.fatigue(),
+        // this will panic while spawning
+        ticks_to_live: if spawning {
+            None
+        } else {
+            Some(creep.ticks_to_live())
+        },
+    }
+}
+
+fn spawn_state(spawn: &StructureSpawn) -> SpawnState {
+    let room = spawn.room();
+    SpawnState {
+        id: spawn.id(),
+        name: spawn.name(),
+        pos: spawn.pos(),
+        spawning: spawn.spawning().map(|spawning| SpawningCreep {
+            name: spawning.name(),
+            need_time: spawning.need_time(),
+            remaining_time: spawning.remaining_time(),
+            directions: spawning.directions(),
+        }),
+        energy: spawn.energy(),
+        energy_capacity: spawn.energy_capacity(),
+        room_energy: room.energy_available(),
+        room_energy_capacity: room.energy_capacity_available(),
+        hits: spawn.hits(),
+        hits_max: spawn.hits_max(),
+    }
+}
+
+fn source_state(source: &Source) -> SourceState {
+    SourceState {
+        id: source.id(),
+        pos: source.pos(),
+        energy: source.energy(),
+        energy_capacity: source.energy_capacity(),
+        ticks_to_regeneration: source.ticks_to_regeneration(),
+    }
+}
+
+fn mineral_state(mineral: &Mineral) -> MineralState {
+    // the bindings read `mineralAmount` as a `Density`
+    let amount: u32 = js!(return @{mineral.as_ref()}.mineralAmount;)
+        .try_into()
+        .unwrap_or(0);
+    MineralState {
+        id: mineral.id(),
+        pos: mineral.pos(),
+        mineral_type: mineral.mineral_type(),
+        amount,
+        ticks_to_regeneration: mineral.ticks_to_regeneration(),
+    }
+}
+
+fn hostile_creep_state(creep: &screeps::Creep) -> HostileCreepState {
+    HostileCreepState {
+        id: creep.id(),
+        name: creep.name(),
+        owner: creep.owner_name(),
+        pos: creep.pos(),
+        hits: creep.hits(),
+        hits_max: creep.hits_max(),
+        body: creep
+            .body()
+            .into_iter()
+            .filter(|part| part.hits > 0)
+            .map(|part| part.part)
+            .collect(),
+    }
+}
+
+fn controller_state(controller: &StructureController) -> ControllerState {
+    ControllerState {
+        id: controller.id(),
+        pos: controller.pos(),
+        my: screeps::OwnedStructureProperties::my(controller),
+        level: controller.level(),
+        progress: controller.progress().unwrap_or(0),
+        progress_total: controller.progress_total().unwrap_or(0),
+    }
+}
+
+fn structure_state(structure: &Structure) -> StructureState {
+    let owner = match structure.as_owned() {
+        Some(owned) if owned.my() => Owner::Mine,
+        Some(owned) if owned.has_owner() => Owner::Hostile,
+        _ => Owner::Nobody,
+    };
+    let (hits, hits_max) = structure.as_attackable().map_or((0, 0), |attackable| {
+        (attackable.hits(), attackable.hits_max())
+    });
+    let store = if let Some(store) = structure.as_can_store_energy() {
+        Some(Store {
+            energy: store.energy(),
+            used: store.energy(),
+            capacity: store.energy_capacity(),
+        })
+    } else {
+        structure.as_has_store().map(|store| Store {
+            energy: store.store_of(ResourceType::Energy),
+            used: store.store_total(),
+            capacity: store.store_capacity(),
+        })
+    };
+    StructureState {
+        id: structure.id(),
+        pos: structure.pos(),
+        structure_type: structure.structure_type(),
+        owner,
+        hits,
+        hits_max,
+        store,
+        transferable: structure.as_transferable().is_some(),
+        withdrawable: structure.as_withdrawable().is_some(),
+    }
+}
+
+fn site_state(site: &ConstructionSite) -> SiteState {
+    SiteState {
+        id: site.id(),
+        pos: site.pos(),
+        my: site.my(),
+        structure_type: site.structure_type(),
+        progress: site.progress(),
+        progress_total: site.progress_total(),
+    }
+}
+
+fn dropped_pile(resource: &Resource) -> PileState {
+    PileState {
+        id: resource.id(),
+        kind: PileKind::Dropped,
+        pos: resource.pos(),
+        amount: if resource.resource_type() == ResourceType::Energy {
+            resource.amount()
+        } else {
+            0
+        },
+    }
+}
+
+fn tombstone_pile(tombstone: &Tombstone) -> PileState {
+    PileState {
+        id: tombstone.id(),
+        kind: PileKind::Tombstone,
+        pos: tombstone.pos(),
+        amount: tombstone.store_of(ResourceType::Energy),
+    }
+}
+
+// the bindings don't know about ruins yet, so they are looked up by hand
+fn find_ruins(room: &screeps::Room) -> Vec<PileState> {
+    let ids: Vec<String> = js!(
+        return @{room.as_ref()}.find(FIND_RUINS).map((ruin) => ruin.id);
+    )
+    .try_into()
+    .unwrap_or_default();
+    ids.iter().filter_map(|id| ruin_pile(id)).collect()
+}
+
+fn ruin_pile(id: &str) -> Option<PileState> {
+    let packed: Option<i32> = js!(
+        var ruin = Game.getObjectById(@{id});
+        return ruin ? ruin.pos.__packedPos : null;
+    )
+    .try_into()
+    .ok()?;
+    let amount: u32 = js!(
+        var ruin = Game.getObjectById(@{id});
+        return ruin ? (ruin.store[RESOURCE_ENERGY] || 0) : 0;
+    )
+    .try_into()
+    .ok()?;
+    packed.map(|packed| PileState {
+        id: id.to_string(),
+        kind: PileKind::Ruin,
+        pos: Position::from_packed(packed),
+        amount,
+    })
+}
+
+fn get_object<T>(id: &str) -> Option<T>
+where
+    T: HasId + SizedRoomObject,
+{
+    match screeps::game::get_object_typed(id) {
+        Ok(object) => object,
+        Err(error) => {
+            warn!("object {} has the wrong type: {:?}", id, error);
+            None
+        }
+    }
+}
+
+fn with_creep<F>(name: &str, action: F) -> ReturnCode
+where
+    F: FnOnce(&screeps::Creep) -> Option<ReturnCode>,
+{
+    match screeps::game::creeps::get(name) {
+        Some(creep) => action(&creep).unwrap_or(ReturnCode::InvalidTarget),
+        None => ReturnCode::NotFound,
+    }
+}
+
+impl GameApi for JsApi {
+    fn time(&self) -> u32 {
+        screeps::game::time()
+    }
+
+    fn cpu_used(&self) -> f64 {
+        screeps::game::cpu::get_used()
+    }
+
+    fn creeps(&self) -> Vec<CreepState> {
+        screeps::game::creeps::values()
+            .iter()
+            .map(creep_state)
+            .collect()
+    }
+
+    fn spawns(&self) -> Vec<SpawnState> {
+        screeps::game::spawns::values()
+            .iter()
+            .map(spawn_state)
+            .collect()
+    }
+
+    fn rooms(&self) -> Vec<RoomName> {
+        screeps::game::rooms::hashmap().keys().copied().collect()
+    }
+
+    fn terrain(&self, pos: Position) -> Terrain {
+        screeps::game::map::get_room_terrain(pos.room_name()).get(pos.x(), pos.y())
+    }
+
+    fn find_sources(&self, room: RoomName) -> Vec<SourceState> {
+        screeps::game::rooms::get(room).map_or_else(Vec::new, |room| {
+            room.find(find::SOURCES).iter().map(source_state).collect()
+        })
+    }
+
+    fn find_minerals(&self, room: RoomName) -> Vec<MineralState> {
+        screeps::game::rooms::get(room).map_or_else(Vec::new, |room| {
+            room.find(find::MINERALS)
+                .iter()
+                .map(mineral_state)
+                .collect()
+        })
+    }
+
+    fn find_hostile_creeps(&self, room: RoomName) -> Vec<HostileCreepState> {
+        screeps::game::rooms::get(room).map_or_else(Vec::new, |room| {
+            room.find(find::HOSTILE_CREEPS)
+                .iter()
+                .map(hostile_creep_state)
+                .collect()
+        })
+    }
+
+    fn room_controller(&self, room: RoomName) -> Option<ControllerState> {
+        let controller = screeps::game::rooms::get(room)?.controller()?;
+        Some(controller_state(&controller))
+    }
+
+    fn find_structures(&self, room: RoomName) -> Vec<StructureState> {
+        screeps::game::rooms::get(room).map_or_else(Vec::new, |room| {
+            room.find(find::STRUCTURES)
+                .iter()
+                .map(structure_state)
+                .collect()
+        })
+    }
+
+    fn find_construction_sites(&self, room: RoomName) -> Vec<SiteState> {
+        screeps::game::rooms::get(room).map_or_else(Vec::new, |room| {
+            room.find(find::CONSTRUCTION_SITES)
+                .iter()
+                .map(site_state)
+                .collect()
+        })
+    }
+
+    fn find_piles(&self, room: RoomName) -> Vec<PileState> {
+        let room = match screeps::game::rooms::get(room) {
+            Some(room) => room,
+            None => return vec![],
+        };
+        let dropped = room.find(find::DROPPED_RESOURCES);
+        let tombstones = room.find(find::TOMBSTONES);
+        dropped
+            .iter()
+            .map(dropped_pile)
+            .chain(tombstones.iter().map(tombstone_pile))
+            .chain(find_ruins(&room))
+            .filter(|pile| pile.amount > 0)
+            .collect()
+    }
+
+    fn get_source(&self, id: &str) -> Option<SourceState> {
+        get_object::<Source>(id).map(|source| source_state(&source))
+    }
+
+    fn get_controller(&self, id: &str) -> Option<ControllerState> {
+        get_object::<StructureController>(id).map(|controller| controller_state(&controller))
+    }
+
+    fn get_structure(&self, id: &str) -> Option<StructureState> {
+        get_object::<Structure>(id).map(|structure| structure_state(&structure))
+    }
+
+    fn get_construction_site(&self, id: &str) -> Option<SiteState> {
+        get_object::<ConstructionSite>(id).map(|site| site_state(&site))
+    }
+
+    fn get_pile(&self, id: &str, kind: PileKind) -> Option<PileState> {
+        match kind {
+            PileKind::Dropped => get_object::<Resource>(id).map(|resource| dropped_pile(&resource)),
+            PileKind::Tombstone => {
+                get_object::<Tombstone>(id).map(|tombstone| tombstone_pile(&tombstone))
+            }
+            PileKind::Ruin => ruin_pile(id),
+        }
+    }
+
+    fn creep_memory(&self, creep: &str, key: &str) -> Option<String> {
+        let memory = screeps::memory::root()
+            .dict("creeps")
+            .ok()??
+            .dict(creep)
+            .ok()??;
+        match memory.string(key) {
+            Ok(value) => value,
+            Err(error) => {
+                error!("could not read {} of {}: {:?}", key, creep, error);
+                None
+            }
+        }
+    }
+
+    fn set_creep_memory(&self, creep: &str, key: &str, value: Option<&str>) {
+        let memory = screeps::memory::root()
+            .dict_or_create("creeps")
+            .and_then(|creeps| creeps.dict_or_create(creep));
+        match (memory, value) {
+            (Ok(memory), Some(value)) => memory.set(key, value),
+            (Ok(memory), None) => memory.del(key),
+            (Err(error), _) => error!("could not write {} of {}: {:?}", key, creep, error),
+        }
+    }
+
+    fn creep_memory_names(&self) -> Vec<String> {
+        match screeps::memory::root().dict("creeps") {
+            Ok(Some(creeps)) => creeps.keys(),
+            Ok(None) => vec![],
+            Err(error) => {
+                warn!("Memory.creeps is not a regular memory object: {:?}", error);
+                vec![]
+            }
+        }
+    }
+
+    fn delete_creep_memory(&self, creep: &str) {
+        if let Ok(Some(creeps)) = screeps::memory::root().dict("creeps") {
+            creeps.del(creep);
+        }
+    }
+
+    fn segment(&self, id: u32) -> Option<String> {
+        screeps::raw_memory::get_segment(id)
+    }
+
+    fn set_segment(&self, id: u32, data: &str) {
+        screeps::raw_memory::set_segment(id, data)
+    }
+
+    fn set_active_segments(&self, ids: &[u32]) {
+        screeps::raw_memory::set_active_segments(ids)
+    }
+
+    fn find_path(
+        &self,
+        from: Position,
+        to: Position,
+        range: u32,
+        matrices: &HashMap<RoomName, LocalCostMatrix>,
+    ) -> Vec<Position> {
+        // the callback has to own what it hands out, so it gets its own copy
+        let matrices = matrices.clone();
+        let room_callback = move |room: String| {
+            room.parse::<RoomName>()
+                .ok()
+                .and_then(|room| matrices.get(&room))
+                .map_or_else(CostMatrix::default, LocalCostMatrix::upload)
+        };
+        let options = SearchOptions::new()
+            .plain_cost(2)
+            .swamp_cost(10)
+            .room_callback(room_callback);
+        pathfinder::search(&from, &to, range, options).load_local_path()
+    }
+
+    fn move_to(&self, creep: &str, target: Position) -> ReturnCode {
+        with_creep(creep, |creep| Some(creep.move_to(&target)))
+    }
+
+    fn move_direction(&self, creep: &str, direction: Direction) -> ReturnCode {
+        with_creep(creep, |creep| Some(creep.move_direction(direction)))
+    }
+
+    fn harvest(&self, creep: &str, source_id: &str) -> ReturnCode {
+        with_creep(creep, |creep| {
+            Some(creep.harvest(&get_object::<Source>(source_id)?))
+        })
+    }
+
+    fn build(&self, creep: &str, site_id: &str) -> ReturnCode {
+        with_creep(creep, |creep| {
+            Some(creep.build(&get_object::<ConstructionSite>(site_id)?))
+        })
+    }
+
+    fn upgrade_controller(&self, creep: &str, controller_id: &str) -> ReturnCode {
+        with_creep(creep, |creep| {
+            Some(creep.upgrade_controller(&get_object::<StructureController>(controller_id)?))
+        })
+    }
+
+    fn transfer_energy(&self, creep: &str, target_id: &str) -> ReturnCode {
+        with_creep(creep, |creep| {
+            let target = get_object::<Structure>(target_id)?;
+            Some(creep.transfer_all(target.as_transferable()?, ResourceType::Energy))
+        })
+    }
+
+    fn withdraw_energy(&self, creep: &str, target_id: &str) -> ReturnCode {
+        with_creep(creep, |creep| {
+            js!(
+                return @{creep.as_ref()}.withdraw(Game.getObjectById(@{target_id}), RESOURCE_ENERGY);
+            )
+            .try_into()
+            .ok()
+        })
+    }
+
+    fn repair(&self, creep: &str, target_id: &str) -> ReturnCode {
+        with_creep(creep, |creep| {
+            Some(creep.repair(&get_object::<Structure>(target_id)?))
+        })
+    }
+
+    fn dismantle(&self, creep: &str, target_id: &str) -> ReturnCode {
+        with_creep(creep, |creep| {
+            Some(creep.dismantle(&get_object::<Structure>(target_id)?))
+        })
+    }
+
+    fn pickup(&self, creep: &str, resource_id: &str) -> ReturnCode {
+        with_creep(creep, |creep| {
+            Some(creep.pickup(&get_object::<Resource>(resource_id)?))
+        })
+    }
+
+    fn spawn_creep(
+        &self,
+        spawn: &str,
+        body: &[Part],
+        name: &str,
+        memory: &[(String, serde_json::Value)],
+    ) -> ReturnCode {
+        let spawn = match screeps::game::spawns::get(spawn) {
+            Some(spawn) => spawn,
+            None => return ReturnCode::NotFound,
+        };
+        if memory.is_empty() {
+            return spawn.spawn_creep(body, name);
+        }
+        let reference = MemoryReference::new();
+        for (key, value) in memory {
+            let value = value.to_string();
+            js! { @(no_return)
+                @{reference.as_ref()}[@{key}] = JSON.parse(@{value});
+            }
+        }
+        let options = SpawnOptions::new().memory(reference);
+        spawn.spawn_creep_with_options(body, name, &options)
+    }
+
+    fn draw_circle(&self, pos: Position) {
+        let room = pos.room_name();
+        let x = pos.x();
+        let y = pos.y();
+        js! {
+            console.addVisual(@{room}, {t: 'c', x: @{x}, y: @{y},
+            s: {radius: 0.55, fill: "transparent", stroke: "red"}});
+        }
+    }
+}
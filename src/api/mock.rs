@@ -0,0 +1,824 @@
+use crate::api::{
+    set_api, ControllerState, CreepState, GameApi, HostileCreepState, MineralState, Owner,
+    PileState, SiteState, SourceState, SpawnState, Store, StructureState,
+};
+use crate::data::body_cost;
+use crate::tasks::PileKind;
+use crate::travel::{offset, DIRECTIONS};
+use screeps::pathfinder::LocalCostMatrix;
+use screeps::{
+    Direction, HasPosition, Part, Position, ResourceType, ReturnCode, RoomName, StructureType,
+    Terrain,
+};
+use serde_json::{Map, Value};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::rc::Rc;
+
+/// Something a creep or spawn was told to do this tick.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Intent {
+    Move {
+        creep: String,
+        target: Position,
+    },
+    Step {
+        creep: String,
+        direction: Direction,
+    },
+    Harvest {
+        creep: String,
+        target: String,
+    },
+    Build {
+        creep: String,
+        target: String,
+    },
+    UpgradeController {
+        creep: String,
+        target: String,
+    },
+    Transfer {
+        creep: String,
+        target: String,
+    },
+    Withdraw {
+        creep: String,
+        target: String,
+    },
+    Repair {
+        creep: String,
+        target: String,
+    },
+    Dismantle {
+        creep: String,
+        target: String,
+    },
+    Pickup {
+        creep: String,
+        target: String,
+    },
+    Spawn {
+        spawn: String,
+        name: String,
+        body: Vec<Part>,
+    },
+}
+
+/// Everything a [`MockApi`] knows about, in ordered maps so iterating over it is deterministic.
+#[derive(Debug, Default)]
+pub struct World {
+    pub time: u32,
+    pub cpu_used: f64,
+    /// Tiles that aren't plain.
+    pub terrain: HashMap<Position, Terrain>,
+    pub creeps: BTreeMap<String, CreepState>,
+    pub spawns: BTreeMap<String, SpawnState>,
+    pub sources: BTreeMap<String, SourceState>,
+    pub minerals: BTreeMap<String, MineralState>,
+    pub hostiles: BTreeMap<String, HostileCreepState>,
+    pub controllers: BTreeMap<String, ControllerState>,
+    pub structures: BTreeMap<String, StructureState>,
+    pub sites: BTreeMap<String, SiteState>,
+    pub piles: BTreeMap<String, PileState>,
+    /// `Memory.creeps`.
+    pub memory: BTreeMap<String, Map<String, Value>>,
+    /// `RawMemory.segments`, whether they're active or not.
+    pub segments: BTreeMap<u32, String>,
+    /// Active segments along with the tick they were asked for, they're readable from the
+    /// next one on.
+    pub active_segments: BTreeMap<u32, u32>,
+    /// Intents issued since they were last taken.
+    pub intents: Vec<Intent>,
+    last_id: u32,
+}
+
+impl World {
+    pub fn new() -> World {
+        World::default()
+    }
+
+    /// A fresh object id.
+    pub fn next_id(&mut self) -> String {
+        self.last_id += 1;
+        format!("mock{}", self.last_id)
+    }
+
+    pub fn add_creep(&mut self, name: &str, pos: Position, carry_capacity: u32) -> &mut CreepState {
+        let creep = CreepState {
+            name: name.to_string(),
+            pos,
+            spawning: false,
+            carry_total: 0,
+            carry_capacity,
+            energy: 0,
+            fatigue: 0,
+            ticks_to_live: Some(1500),
+        };
+        self.creeps.insert(name.to_string(), creep);
+        self.creeps.get_mut(name).unwrap()
+    }
+
+    /// Adds a full spawn, which shows up as a structure as well.
+    pub fn add_spawn(&mut self, name: &str, pos: Position) -> &mut SpawnState {
+        let structure = self.add_structure(pos, StructureType::Spawn);
+        structure.hits = 5000;
+        structure.hits_max = 5000;
+        structure.store = Some(Store {
+            energy: 300,
+            used: 300,
+            capacity: 300,
+        });
+        structure.transferable = true;
+        structure.withdrawable = true;
+        let id = structure.id.clone();
+        let spawn = SpawnState {
+            id,
+            name: name.to_string(),
+            pos,
+            spawning: None,
+            energy: 300,
+            energy_capacity: 300,
+            room_energy: 300,
+            room_energy_capacity: 300,
+            hits: 5000,
+            hits_max: 5000,
+        };
+        self.spawns.insert(name.to_string(), spawn);
+        self.spawns.get_mut(name).unwrap()
+    }
+
+    pub fn add_source(&mut self, pos: Position) -> &mut SourceState {
+        let id = self.next_id();
+        let source = SourceState {
+            id: id.clone(),
+            pos,
+            energy: 3000,
+            energy_capacity: 3000,
+            ticks_to_regeneration: 300,
+        };
+        self.sources.entry(id).or_insert(source)
+    }
+
+    pub fn add_mineral(&mut self, pos: Position, mineral_type: ResourceType) -> &mut MineralState {
+        let id = self.next_id();
+        let mineral = MineralState {
+            id: id.clone(),
+            pos,
+            mineral_type,
+            amount: 35_000,
+            ticks_to_regeneration: 0,
+        };
+        self.minerals.entry(id).or_insert(mineral)
+    }
+
+    pub fn add_hostile(
+        &mut self,
+        name: &str,
+        owner: &str,
+        pos: Position,
+    ) -> &mut HostileCreepState {
+        let id = self.next_id();
+        let hostile = HostileCreepState {
+            id: id.clone(),
+            name: name.to_string(),
+            owner: owner.to_string(),
+            pos,
+            hits: 100,
+            hits_max: 100,
+            body: vec![],
+        };
+        self.hostiles.entry(id).or_insert(hostile)
+    }
+
+    pub fn add_controller(&mut self, pos: Position) -> &mut ControllerState {
+        let id = self.next_id();
+        let controller = ControllerState {
+            id: id.clone(),
+            pos,
+            my: true,
+            level: 1,
+            progress: 0,
+            progress_total: 200,
+        };
+        self.controllers.entry(id).or_insert(controller)
+    }
+
+    /// Adds one of our structures without hits or store, fill in whatever the test needs.
+    pub fn add_structure(
+        &mut self,
+        pos: Position,
+        structure_type: StructureType,
+    ) -> &mut StructureState {
+        let id = self.next_id();
+        let structure = StructureState {
+            id: id.clone(),
+            pos,
+            structure_type,
+            owner: Owner::Mine,
+            hits: 0,
+            hits_max: 0,
+            store: None,
+            transferable: false,
+            withdrawable: false,
+        };
+        self.structures.entry(id).or_insert(structure)
+    }
+
+    pub fn add_construction_site(
+        &mut self,
+        pos: Position,
+        structure_type: StructureType,
+        progress_total: u32,
+    ) -> &mut SiteState {
+        let id = self.next_id();
+        let site = SiteState {
+            id: id.clone(),
+            pos,
+            my: true,
+            structure_type,
+            progress: 0,
+            progress_total,
+        };
+        self.sites.entry(id).or_insert(site)
+    }
+
+    pub fn add_pile(&mut self, pos: Position, kind: PileKind, amount: u32) -> &mut PileState {
+        let id = self.next_id();
+        let pile = PileState {
+            id: id.clone(),
+            kind,
+            pos,
+            amount,
+        };
+        self.piles.entry(id).or_insert(pile)
+    }
+
+    fn target_pos(&self, id: &str) -> Option<Position> {
+        self.sources
+            .get(id)
+            .map(HasPosition::pos)
+            .or_else(|| self.controllers.get(id).map(HasPosition::pos))
+            .or_else(|| self.structures.get(id).map(HasPosition::pos))
+            .or_else(|| self.sites.get(id).map(HasPosition::pos))
+            .or_else(|| self.piles.get(id).map(HasPosition::pos))
+    }
+
+    /// Checks what the game checks before accepting an intent of `creep` on `target`.
+    fn check_action(
+        &self,
+        creep: &str,
+        target: &str,
+        range: u32,
+    ) -> Result<&CreepState, ReturnCode> {
+        let creep = self.creeps.get(creep).ok_or(ReturnCode::NotFound)?;
+        if creep.spawning {
+            return Err(ReturnCode::Busy);
+        }
+        let pos = self.target_pos(target).ok_or(ReturnCode::InvalidTarget)?;
+        if pos.room_name() != creep.pos.room_name() || creep.pos.get_range_to(&pos) > range {
+            return Err(ReturnCode::NotInRange);
+        }
+        Ok(creep)
+    }
+
+    fn spawn_name_taken(&self, name: &str) -> bool {
+        self.creeps.contains_key(name)
+            || self.spawns.values().any(|spawn| {
+                spawn
+                    .spawning
+                    .as_ref()
+                    .is_some_and(|spawning| spawning.name == name)
+            })
+            || self.intents.iter().any(|intent| match intent {
+                Intent::Spawn { name: spawning, .. } => spawning == name,
+                _ => false,
+            })
+    }
+}
+
+/// An in-memory stand-in for the game, recording intents instead of acting on them.
+#[derive(Debug, Default)]
+pub struct MockApi {
+    world: RefCell<World>,
+}
+
+impl MockApi {
+    pub fn new() -> MockApi {
+        MockApi::default()
+    }
+
+    pub fn with_world(world: World) -> MockApi {
+        MockApi {
+            world: RefCell::new(world),
+        }
+    }
+
+    /// Creates an empty mock and makes it the API of the current thread.
+    pub fn install() -> Rc<MockApi> {
+        let mock = Rc::new(MockApi::new());
+        set_api(mock.clone());
+        mock
+    }
+
+    pub fn world(&self) -> Ref<'_, World> {
+        self.world.borrow()
+    }
+
+    pub fn world_mut(&self) -> RefMut<'_, World> {
+        self.world.borrow_mut()
+    }
+
+    /// Intents issued since the last call.
+    pub fn take_intents(&self) -> Vec<Intent> {
+        std::mem::take(&mut self.world.borrow_mut().intents)
+    }
+
+    fn act<F>(&self, creep: &str, target: &str, range: u32, check: F, intent: Intent) -> ReturnCode
+    where
+        F: FnOnce(&World, &CreepState) -> ReturnCode,
+    {
+        let mut world = self.world.borrow_mut();
+        let r = match world.check_action(creep, target, range) {
+            Ok(state) => check(&world, state),
+            Err(r) => r,
+        };
+        if r == ReturnCode::Ok {
+            world.intents.push(intent);
+        }
+        r
+    }
+}
+
+fn has_energy(creep: &CreepState) -> ReturnCode {
+    if creep.energy == 0 {
+        ReturnCode::NotEnough
+    } else {
+        ReturnCode::Ok
+    }
+}
+
+fn has_room(creep: &CreepState) -> ReturnCode {
+    if creep.carry_total >= creep.carry_capacity {
+        ReturnCode::Full
+    } else {
+        ReturnCode::Ok
+    }
+}
+
+impl GameApi for MockApi {
+    fn time(&self) -> u32 {
+        self.world.borrow().time
+    }
+
+    fn cpu_used(&self) -> f64 {
+        self.world.borrow().cpu_used
+    }
+
+    fn creeps(&self) -> Vec<CreepState> {
+        self.world.borrow().creeps.values().cloned().collect()
+    }
+
+    fn spawns(&self) -> Vec<SpawnState> {
+        self.world.borrow().spawns.values().cloned().collect()
+    }
+
+    fn rooms(&self) -> Vec<RoomName> {
+        let world = self.world.borrow();
+        let mut rooms: Vec<RoomName> = world
+            .creeps
+            .values()
+            .map(|creep| creep.pos.room_name())
+            .chain(world.structures.values().map(|s| s.pos.room_name()))
+            .chain(world.controllers.values().map(|c| c.pos.room_name()))
+            .chain(world.sources.values().map(|s| s.pos.room_name()))
+            .collect();
+        rooms.sort_by_key(|room| room.to_string());
+        rooms.dedup();
+        rooms
+    }
+
+    fn terrain(&self, pos: Position) -> Terrain {
+        let world = self.world.borrow();
+        world.terrain.get(&pos).copied().unwrap_or(Terrain::Plain)
+    }
+
+    fn find_sources(&self, room: RoomName) -> Vec<SourceState> {
+        let world = self.world.borrow();
+        in_room(world.sources.values(), room)
+    }
+
+    fn find_minerals(&self, room: RoomName) -> Vec<MineralState> {
+        let world = self.world.borrow();
+        in_room(world.minerals.values(), room)
+    }
+
+    fn find_hostile_creeps(&self, room: RoomName) -> Vec<HostileCreepState> {
+        let world = self.world.borrow();
+        in_room(world.hostiles.values(), room)
+    }
+
+    fn room_controller(&self, room: RoomName) -> Option<ControllerState> {
+        let world = self.world.borrow();
+        in_room(world.controllers.values(), room).pop()
+    }
+
+    fn find_structures(&self, room: RoomName) -> Vec<StructureState> {
+        let world = self.world.borrow();
+        in_room(world.structures.values(), room)
+    }
+
+    fn find_construction_sites(&self, room: RoomName) -> Vec<SiteState> {
+        let world = self.world.borrow();
+        in_room(world.sites.values(), room)
+    }
+
+    fn find_piles(&self, room: RoomName) -> Vec<PileState> {
+        let world = self.world.borrow();
+        let piles = world.piles.values().filter(|pile| pile.amount > 0);
+        in_room(piles, room)
+    }
+
+    fn get_source(&self, id: &str) -> Option<SourceState> {
+        self.world.borrow().sources.get(id).cloned()
+    }
+
+    fn get_controller(&self, id: &str) -> Option<ControllerState> {
+        self.world.borrow().controllers.get(id).cloned()
+    }
+
+    fn get_structure(&self, id: &str) -> Option<StructureState> {
+        self.world.borrow().structures.get(id).cloned()
+    }
+
+    fn get_construction_site(&self, id: &str) -> Option<SiteState> {
+        self.world.borrow().sites.get(id).cloned()
+    }
+
+    fn get_pile(&self, id: &str, kind: PileKind) -> Option<PileState> {
+        let world = self.world.borrow();
+        world
+            .piles
+            .get(id)
+            .filter(|pile| pile.kind == kind)
+            .cloned()
+    }
+
+    fn creep_memory(&self, creep: &str, key: &str) -> Option<String> {
+        let world = self.world.borrow();
+        let value = world.memory.get(creep)?.get(key)?;
+        value.as_str().map(str::to_string)
+    }
+
+    fn set_creep_memory(&self, creep: &str, key: &str, value: Option<&str>) {
+        let mut world = self.world.borrow_mut();
+        let memory = world.memory.entry(creep.to_string()).or_default();
+        match value {
+            Some(value) => {
+                memory.insert(key.to_string(), Value::String(value.to_string()));
+            }
+            None => {
+                memory.remove(key);
+            }
+        }
+    }
+
+    fn creep_memory_names(&self) -> Vec<String> {
+        self.world.borrow().memory.keys().cloned().collect()
+    }
+
+    fn delete_creep_memory(&self, creep: &str) {
+        self.world.borrow_mut().memory.remove(creep);
+    }
+
+    /// Active segments nothing was written to read as empty, like they do in the game.
+    fn segment(&self, id: u32) -> Option<String> {
+        let world = self.world.borrow();
+        match world.active_segments.get(&id) {
+            Some(since) if world.time > *since => {
+                Some(world.segments.get(&id).cloned().unwrap_or_default())
+            }
+            _ => None,
+        }
+    }
+
+    fn set_segment(&self, id: u32, data: &str) {
+        let mut world = self.world.borrow_mut();
+        world.segments.insert(id, data.to_string());
+    }
+
+    fn set_active_segments(&self, ids: &[u32]) {
+        let mut world = self.world.borrow_mut();
+        let time = world.time;
+        // segments that stay active keep being readable
+        let active = ids
+            .iter()
+            .map(|id| (*id, world.active_segments.get(id).cloned().unwrap_or(time)))
+            .collect();
+        world.active_segments = active;
+    }
+
+    /// Breadth-first search over the rooms of `from` and `to`, which ignores the costs of
+    /// passable tiles.
+    fn find_path(
+        &self,
+        from: Position,
+        to: Position,
+        range: u32,
+        matrices: &HashMap<RoomName, LocalCostMatrix>,
+    ) -> Vec<Position> {
+        let world = self.world.borrow();
+        let passable = |pos: Position| {
+            let cost = matrices
+                .get(&pos.room_name())
+                .map_or(0, |matrix| matrix.get(pos.x() as u8, pos.y() as u8));
+            cost < 255 && world.terrain.get(&pos) != Some(&Terrain::Wall)
+        };
+        let rooms = [from.room_name(), to.room_name()];
+
+        let mut previous: HashMap<Position, Position> = HashMap::new();
+        let mut queue = VecDeque::new();
+        let mut closest = from;
+        queue.push_back(from);
+        previous.insert(from, from);
+        while let Some(pos) = queue.pop_front() {
+            if pos.get_range_to(&to) < closest.get_range_to(&to) {
+                closest = pos;
+            }
+            if pos.get_range_to(&to) <= range {
+                break;
+            }
+            for direction in DIRECTIONS.iter() {
+                // stepping off the edge of a room leads onto the edge of the next one
+                let next = pos + offset(*direction);
+                if previous.contains_key(&next)
+                    || !rooms.contains(&next.room_name())
+                    || !passable(next)
+                {
+                    continue;
+                }
+                previous.insert(next, pos);
+                queue.push_back(next);
+            }
+        }
+
+        let mut path = vec![];
+        let mut pos = closest;
+        while pos != from {
+            path.push(pos);
+            pos = previous[&pos];
+        }
+        path.reverse();
+        path
+    }
+
+    fn move_to(&self, creep: &str, target: Position) -> ReturnCode {
+        let mut world = self.world.borrow_mut();
+        match world.creeps.get(creep) {
+            None => ReturnCode::NotFound,
+            Some(state) if state.spawning => ReturnCode::Busy,
+            Some(_) => {
+                world.intents.push(Intent::Move {
+                    creep: creep.to_string(),
+                    target,
+                });
+                ReturnCode::Ok
+            }
+        }
+    }
+
+    fn move_direction(&self, creep: &str, direction: Direction) -> ReturnCode {
+        let mut world = self.world.borrow_mut();
+        match world.creeps.get(creep) {
+            None => ReturnCode::NotFound,
+            Some(state) if state.spawning => ReturnCode::Busy,
+            Some(state) if state.fatigue > 0 => ReturnCode::Tired,
+            Some(_) => {
+                world.intents.push(Intent::Step {
+                    creep: creep.to_string(),
+                    direction,
+                });
+                ReturnCode::Ok
+            }
+        }
+    }
+
+    fn harvest(&self, creep: &str, source_id: &str) -> ReturnCode {
+        let intent = Intent::Harvest {
+            creep: creep.to_string(),
+            target: source_id.to_string(),
+        };
+        let check = |world: &World, _: &CreepState| match world.sources.get(source_id) {
+            Some(source) if source.energy > 0 => ReturnCode::Ok,
+            Some(_) => ReturnCode::NotEnough,
+            None => ReturnCode::InvalidTarget,
+        };
+        self.act(creep, source_id, 1, check, intent)
+    }
+
+    fn build(&self, creep: &str, site_id: &str) -> ReturnCode {
+        let intent = Intent::Build {
+            creep: creep.to_string(),
+            target: site_id.to_string(),
+        };
+        let check = |world: &World, creep: &CreepState| match world.sites.get(site_id) {
+            Some(_) => has_energy(creep),
+            None => ReturnCode::InvalidTarget,
+        };
+        self.act(creep, site_id, 3, check, intent)
+    }
+
+    fn upgrade_controller(&self, creep: &str, controller_id: &str) -> ReturnCode {
+        let intent = Intent::UpgradeController {
+            creep: creep.to_string(),
+            target: controller_id.to_string(),
+        };
+        let check = |world: &World, creep: &CreepState| match world.controllers.get(controller_id) {
+            Some(controller) if controller.my => has_energy(creep),
+            Some(_) => ReturnCode::NotOwner,
+            None => ReturnCode::InvalidTarget,
+        };
+        self.act(creep, controller_id, 3, check, intent)
+    }
+
+    fn transfer_energy(&self, creep: &str, target_id: &str) -> ReturnCode {
+        let intent = Intent::Transfer {
+            creep: creep.to_string(),
+            target: target_id.to_string(),
+        };
+        let check = |world: &World, creep: &CreepState| match world
+            .structures
+            .get(target_id)
+            .and_then(StructureState::free_energy_capacity)
+        {
+            Some(0) => ReturnCode::Full,
+            Some(_) => has_energy(creep),
+            None => ReturnCode::InvalidTarget,
+        };
+        self.act(creep, target_id, 1, check, intent)
+    }
+
+    fn withdraw_energy(&self, creep: &str, target_id: &str) -> ReturnCode {
+        let intent = Intent::Withdraw {
+            creep: creep.to_string(),
+            target: target_id.to_string(),
+        };
+        let check = |world: &World, creep: &CreepState| {
+            let stored = world
+                .structures
+                .get(target_id)
+                .and_then(StructureState::stored_energy)
+                .or_else(|| {
+                    world
+                        .piles
+                        .get(target_id)
+                        .filter(|pile| pile.kind != PileKind::Dropped)
+                        .map(|pile| pile.amount)
+                });
+            match stored {
+                Some(0) => ReturnCode::NotEnough,
+                Some(_) => has_room(creep),
+                None => ReturnCode::InvalidTarget,
+            }
+        };
+        self.act(creep, target_id, 1, check, intent)
+    }
+
+    fn repair(&self, creep: &str, target_id: &str) -> ReturnCode {
+        let intent = Intent::Repair {
+            creep: creep.to_string(),
+            target: target_id.to_string(),
+        };
+        let check = |world: &World, creep: &CreepState| match world.structures.get(target_id) {
+            Some(structure) if structure.hits_max > 0 => has_energy(creep),
+            _ => ReturnCode::InvalidTarget,
+        };
+        self.act(creep, target_id, 3, check, intent)
+    }
+
+    fn dismantle(&self, creep: &str, target_id: &str) -> ReturnCode {
+        let intent = Intent::Dismantle {
+            creep: creep.to_string(),
+            target: target_id.to_string(),
+        };
+        let check = |world: &World, _: &CreepState| match world.structures.get(target_id) {
+            Some(structure) if structure.hits_max > 0 => ReturnCode::Ok,
+            _ => ReturnCode::InvalidTarget,
+        };
+        self.act(creep, target_id, 1, check, intent)
+    }
+
+    fn pickup(&self, creep: &str, resource_id: &str) -> ReturnCode {
+        let intent = Intent::Pickup {
+            creep: creep.to_string(),
+            target: resource_id.to_string(),
+        };
+        let check = |world: &World, creep: &CreepState| match world.piles.get(resource_id) {
+            Some(pile) if pile.kind == PileKind::Dropped => has_room(creep),
+            _ => ReturnCode::InvalidTarget,
+        };
+        self.act(creep, resource_id, 1, check, intent)
+    }
+
+    fn spawn_creep(
+        &self,
+        spawn: &str,
+        body: &[Part],
+        name: &str,
+        memory: &[(String, Value)],
+    ) -> ReturnCode {
+        let mut world = self.world.borrow_mut();
+        let state = match world.spawns.get(spawn) {
+            Some(state) => state,
+            None => return ReturnCode::NotFound,
+        };
+        if body.is_empty() {
+            return ReturnCode::InvalidArgs;
+        }
+        if world.spawn_name_taken(name) {
+            return ReturnCode::NameExists;
+        }
+        if state.spawning.is_some() {
+            return ReturnCode::Busy;
+        }
+        if body_cost(body) > state.room_energy {
+            return ReturnCode::NotEnough;
+        }
+        // like the game, memory is there right away while the creep only shows up next tick
+        let memory = memory.iter().cloned().collect();
+        world.memory.insert(name.to_string(), memory);
+        world.intents.push(Intent::Spawn {
+            spawn: spawn.to_string(),
+            name: name.to_string(),
+            body: body.to_vec(),
+        });
+        ReturnCode::Ok
+    }
+
+    fn draw_circle(&self, _pos: Position) {}
+}
+
+fn in_room<'a, T, I>(states: I, room: RoomName) -> Vec<T>
+where
+    T: HasPosition + Clone + 'a,
+    I: Iterator<Item = &'a T>,
+{
+    states
+        .filter(|state| state.pos().room_name() == room)
+        .cloned()
+        .collect()
+}
+
+/// Setup shared by tests against a [`MockApi`], which all take place in W1N1.
+#[cfg(test)]
+pub mod fixtures {
+    use crate::api::api;
+    use crate::data::{Creep, Reservations, Roles, Room};
+    use crate::tasks::TaskContext;
+    use screeps::{Position, RoomName};
+    use std::collections::HashMap;
+
+    pub fn room_name() -> RoomName {
+        "W1N1".parse().unwrap()
+    }
+
+    pub fn pos(x: u32, y: u32) -> Position {
+        Position::new(x, y, room_name())
+    }
+
+    /// W1N1 as the mock has it now.
+    pub fn rooms() -> HashMap<RoomName, Room> {
+        let name = room_name();
+        vec![(name, Room::from(name))].into_iter().collect()
+    }
+
+    /// The creep called `name` as the mock has it now.
+    pub fn creep(name: &str) -> Creep {
+        let state = api().creeps().into_iter().find(|creep| creep.name == name);
+        Creep::from(state.unwrap(), &Roles::new())
+    }
+
+    /// What a [`TaskContext`] borrows, starting out with no claims and the rooms of [`rooms`].
+    pub struct TaskState {
+        pub reservations: Reservations,
+        pub rooms: HashMap<RoomName, Room>,
+    }
+
+    impl Default for TaskState {
+        fn default() -> TaskState {
+            TaskState {
+                reservations: Reservations::new(),
+                rooms: rooms(),
+            }
+        }
+    }
+
+    impl TaskState {
+        pub fn context(&mut self) -> TaskContext<'_> {
+            TaskContext {
+                reservations: &mut self.reservations,
+                rooms: &mut self.rooms,
+            }
+        }
+    }
+}
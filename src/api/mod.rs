@@ -0,0 +1,308 @@
+//! The game API `data` and `tasks` talk to.
+//!
+//! Objects are handed out as plain snapshots of the current tick and referred to by id, so
+//! the same logic runs against the live game through [`JsApi`] or natively against an
+//! in-memory `MockApi`, which comes with the `sim` feature.
+
+mod js;
+#[cfg(any(test, feature = "sim"))]
+mod mock;
+#[cfg(any(test, feature = "sim"))]
+mod sim;
+
+pub use self::js::JsApi;
+#[cfg(test)]
+pub use self::mock::fixtures;
+#[cfg(any(test, feature = "sim"))]
+pub use self::mock::{Intent, MockApi, World};
+#[cfg(any(test, feature = "sim"))]
+pub use self::sim::Simulator;
+
+use crate::tasks::PileKind;
+use screeps::pathfinder::LocalCostMatrix;
+use screeps::{
+    Direction, HasPosition, Part, Position, ResourceType, ReturnCode, RoomName, StructureType,
+    Terrain,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreepState {
+    pub name: String,
+    pub pos: Position,
+    pub spawning: bool,
+    pub carry_total: u32,
+    pub carry_capacity: u32,
+    pub energy: u32,
+    pub fatigue: u32,
+    /// `None` while the creep is still spawning.
+    pub ticks_to_live: Option<u32>,
+}
+
+/// The creep a spawn is currently working on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawningCreep {
+    pub name: String,
+    pub need_time: u32,
+    pub remaining_time: u32,
+    pub directions: Vec<Direction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnState {
+    pub id: String,
+    pub name: String,
+    pub pos: Position,
+    pub spawning: Option<SpawningCreep>,
+    pub energy: u32,
+    pub energy_capacity: u32,
+    /// Energy available for spawning in the whole room, spawns and extensions.
+    pub room_energy: u32,
+    pub room_energy_capacity: u32,
+    pub hits: u32,
+    pub hits_max: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceState {
+    pub id: String,
+    pub pos: Position,
+    pub energy: u32,
+    pub energy_capacity: u32,
+    pub ticks_to_regeneration: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MineralState {
+    pub id: String,
+    pub pos: Position,
+    pub mineral_type: ResourceType,
+    pub amount: u32,
+    pub ticks_to_regeneration: u32,
+}
+
+/// A creep that isn't ours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostileCreepState {
+    pub id: String,
+    pub name: String,
+    pub owner: String,
+    pub pos: Position,
+    pub hits: u32,
+    pub hits_max: u32,
+    /// Parts that aren't destroyed yet.
+    pub body: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControllerState {
+    pub id: String,
+    pub pos: Position,
+    pub my: bool,
+    pub level: u32,
+    pub progress: u32,
+    pub progress_total: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Owner {
+    Mine,
+    Hostile,
+    /// Structures nobody can own, like roads, walls and containers.
+    Nobody,
+}
+
+/// Resources held by a structure, `capacity` and `used` covering all resource types.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Store {
+    pub energy: u32,
+    pub used: u32,
+    pub capacity: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructureState {
+    pub id: String,
+    pub pos: Position,
+    pub structure_type: StructureType,
+    pub owner: Owner,
+    /// Both 0 for structures that can't be damaged.
+    pub hits: u32,
+    pub hits_max: u32,
+    pub store: Option<Store>,
+    pub transferable: bool,
+    pub withdrawable: bool,
+}
+
+impl StructureState {
+    /// How much more energy the structure can take, `None` for structures that don't take any.
+    pub fn free_energy_capacity(&self) -> Option<u32> {
+        if !self.transferable {
+            return None;
+        }
+        self.store.map(|store| store.capacity - store.used)
+    }
+
+    /// How much energy can be withdrawn, `None` if the structure can't be withdrawn from.
+    pub fn stored_energy(&self) -> Option<u32> {
+        if !self.withdrawable {
+            return None;
+        }
+        self.store.map(|store| store.energy)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteState {
+    pub id: String,
+    pub pos: Position,
+    pub my: bool,
+    pub structure_type: StructureType,
+    pub progress: u32,
+    pub progress_total: u32,
+}
+
+/// Energy lying around, either dropped or left in a tombstone or ruin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PileState {
+    pub id: String,
+    pub kind: PileKind,
+    pub pos: Position,
+    pub amount: u32,
+}
+
+macro_rules! impl_has_position {
+    ($($state:ty),*) => {
+        $(
+            impl HasPosition for $state {
+                fn pos(&self) -> Position {
+                    self.pos
+                }
+            }
+        )*
+    };
+}
+
+impl_has_position!(
+    CreepState,
+    SpawnState,
+    SourceState,
+    MineralState,
+    HostileCreepState,
+    ControllerState,
+    StructureState,
+    SiteState,
+    PileState
+);
+
+pub trait GameApi {
+    fn time(&self) -> u32;
+    fn cpu_used(&self) -> f64;
+
+    /// All of our creeps.
+    fn creeps(&self) -> Vec<CreepState>;
+    /// All of our spawns.
+    fn spawns(&self) -> Vec<SpawnState>;
+
+    /// Rooms we can currently see into.
+    fn rooms(&self) -> Vec<RoomName>;
+    fn terrain(&self, pos: Position) -> Terrain;
+    fn find_sources(&self, room: RoomName) -> Vec<SourceState>;
+    fn find_minerals(&self, room: RoomName) -> Vec<MineralState>;
+    fn find_hostile_creeps(&self, room: RoomName) -> Vec<HostileCreepState>;
+    fn room_controller(&self, room: RoomName) -> Option<ControllerState>;
+    fn find_structures(&self, room: RoomName) -> Vec<StructureState>;
+    fn find_construction_sites(&self, room: RoomName) -> Vec<SiteState>;
+    /// Energy piles with something left in them.
+    fn find_piles(&self, room: RoomName) -> Vec<PileState>;
+
+    fn get_source(&self, id: &str) -> Option<SourceState>;
+    fn get_controller(&self, id: &str) -> Option<ControllerState>;
+    fn get_structure(&self, id: &str) -> Option<StructureState>;
+    fn get_construction_site(&self, id: &str) -> Option<SiteState>;
+    fn get_pile(&self, id: &str, kind: PileKind) -> Option<PileState>;
+
+    /// String stored under `Memory.creeps[creep][key]`.
+    fn creep_memory(&self, creep: &str, key: &str) -> Option<String>;
+    /// Stores `value` under `Memory.creeps[creep][key]`, `None` deletes the key.
+    fn set_creep_memory(&self, creep: &str, key: &str, value: Option<&str>);
+    /// Names of all creeps in `Memory.creeps`, alive or not.
+    fn creep_memory_names(&self) -> Vec<String>;
+    fn delete_creep_memory(&self, creep: &str);
+
+    /// Contents of `RawMemory.segments[id]`, `None` unless the segment was made active.
+    fn segment(&self, id: u32) -> Option<String>;
+    fn set_segment(&self, id: u32, data: &str);
+    /// Makes the segments `ids` readable from the next tick on, the game allows up to 10.
+    fn set_active_segments(&self, ids: &[u32]);
+
+    /// Shortest path from `from` to within `range` of `to`, or as close as it gets, with
+    /// `matrices` overriding the terrain of their rooms.
+    fn find_path(
+        &self,
+        from: Position,
+        to: Position,
+        range: u32,
+        matrices: &HashMap<RoomName, LocalCostMatrix>,
+    ) -> Vec<Position>;
+
+    fn move_to(&self, creep: &str, target: Position) -> ReturnCode;
+    fn move_direction(&self, creep: &str, direction: Direction) -> ReturnCode;
+    fn harvest(&self, creep: &str, source_id: &str) -> ReturnCode;
+    fn build(&self, creep: &str, site_id: &str) -> ReturnCode;
+    fn upgrade_controller(&self, creep: &str, controller_id: &str) -> ReturnCode;
+    fn transfer_energy(&self, creep: &str, target_id: &str) -> ReturnCode;
+    /// Withdraws from structures, tombstones and ruins alike.
+    fn withdraw_energy(&self, creep: &str, target_id: &str) -> ReturnCode;
+    fn repair(&self, creep: &str, target_id: &str) -> ReturnCode;
+    fn dismantle(&self, creep: &str, target_id: &str) -> ReturnCode;
+    fn pickup(&self, creep: &str, resource_id: &str) -> ReturnCode;
+    /// Spawns a creep with `memory` as its initial `Memory.creeps[name]`.
+    fn spawn_creep(
+        &self,
+        spawn: &str,
+        body: &[Part],
+        name: &str,
+        memory: &[(String, serde_json::Value)],
+    ) -> ReturnCode;
+
+    fn draw_circle(&self, pos: Position);
+}
+
+#[cfg(any(target_arch = "wasm32", not(any(test, feature = "sim"))))]
+fn default_api() -> Rc<dyn GameApi> {
+    Rc::new(JsApi)
+}
+
+// there is no game to talk to outside of wasm, so native builds start out with an empty world
+#[cfg(all(not(target_arch = "wasm32"), any(test, feature = "sim")))]
+fn default_api() -> Rc<dyn GameApi> {
+    Rc::new(MockApi::new())
+}
+
+thread_local! {
+    static API: RefCell<Rc<dyn GameApi>> = RefCell::new(default_api());
+    static ACTIVE_SEGMENTS: RefCell<BTreeSet<u32>> = const { RefCell::new(BTreeSet::new()) };
+}
+
+/// The API of the current thread, the live game unless swapped out with [`set_api`].
+pub fn api() -> Rc<dyn GameApi> {
+    API.with(|api| api.borrow().clone())
+}
+
+/// Swaps out the API of the current thread, tests use this to run against a `MockApi`.
+pub fn set_api(api: Rc<dyn GameApi>) {
+    API.with(|current| *current.borrow_mut() = api);
+}
+
+/// Keeps segment `id` readable from the next tick on, along with the others asked for here.
+pub fn activate_segment(id: u32) {
+    let ids: Vec<u32> = ACTIVE_SEGMENTS.with(|active| {
+        let mut active = active.borrow_mut();
+        active.insert(id);
+        active.iter().cloned().collect()
+    });
+    api().set_active_segments(&ids);
+}
@@ -0,0 +1,692 @@
+use crate::api::{
+    set_api, CreepState, Intent, MockApi, Owner, PileState, SpawningCreep, Store, StructureState,
+    World,
+};
+use crate::data::{body_cost, Game};
+use crate::tasks::PileKind;
+use crate::travel::offset;
+use screeps::{
+    controller_levels, Part, Position, RoomName, StructureType, Terrain, BUILD_POWER,
+    CARRY_CAPACITY, CREEP_CLAIM_LIFE_TIME, CREEP_LIFE_TIME, CREEP_SPAWN_TIME, DISMANTLE_POWER,
+    ENERGY_REGEN_TIME, HARVEST_POWER, REPAIR_POWER, SPAWN_ENERGY_CAPACITY,
+    UPGRADE_CONTROLLER_POWER,
+};
+use std::cell::{Ref, RefMut};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::rc::Rc;
+
+/// Body assumed for creeps put into the world without one.
+const DEFAULT_BODY: [Part; 3] = [Part::Work, Part::Carry, Part::Move];
+
+// Top, TopRight, Right, ... like `Direction`, so paths come out the same every run
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// A deterministic stand-in for the game server, resolving the intents issued to a [`MockApi`]
+/// each tick. Decay, fatigue, combat and multiple rooms are left out.
+pub struct Simulator {
+    api: Rc<MockApi>,
+    bodies: BTreeMap<String, Vec<Part>>,
+}
+
+impl Simulator {
+    /// Makes `world` the API of the current thread.
+    pub fn new(world: World) -> Simulator {
+        let api = Rc::new(MockApi::with_world(world));
+        set_api(api.clone());
+        sync_spawns(&mut api.world_mut());
+        Simulator {
+            api,
+            bodies: BTreeMap::new(),
+        }
+    }
+
+    pub fn world(&self) -> Ref<'_, World> {
+        self.api.world()
+    }
+
+    pub fn world_mut(&self) -> RefMut<'_, World> {
+        self.api.world_mut()
+    }
+
+    /// Sets the body of a creep that was put into the world by hand.
+    pub fn set_body(&mut self, creep: &str, body: Vec<Part>) {
+        self.bodies.insert(creep.to_string(), body);
+    }
+
+    pub fn body(&self, creep: &str) -> &[Part] {
+        self.bodies.get(creep).map_or(&DEFAULT_BODY, Vec::as_slice)
+    }
+
+    /// Runs `ticks` ticks of `game` the way the live game loop does.
+    pub fn run(&mut self, game: &mut Game, ticks: u32, game_loop: &dyn Fn(&mut Game)) {
+        for _ in 0..ticks {
+            game.tick(game_loop);
+            self.step();
+        }
+    }
+
+    /// Resolves this tick's intents and advances the world by one tick.
+    pub fn step(&mut self) {
+        let intents = self.api.take_intents();
+        let api = self.api.clone();
+        let mut world = api.world_mut();
+
+        let mut moves: BTreeMap<String, Position> = BTreeMap::new();
+        let mut steps: BTreeMap<String, Position> = BTreeMap::new();
+        for intent in intents {
+            match intent {
+                Intent::Move { creep, target } => {
+                    moves.insert(creep, target);
+                }
+                Intent::Step { creep, direction } => {
+                    if let Some(creep_state) = world.creeps.get(&creep) {
+                        steps.insert(creep, creep_state.pos + offset(direction));
+                    }
+                }
+                Intent::Spawn { spawn, name, body } => self.spawn(&mut world, &spawn, name, body),
+                intent => {
+                    self.act(&mut world, intent);
+                }
+            }
+        }
+        self.step_creeps(&mut world, steps);
+        self.move_creeps(&mut world, moves);
+        self.age_creeps(&mut world);
+        self.finish_spawning(&mut world);
+        regenerate(&mut world);
+        world.piles.retain(|_, pile| pile.amount > 0);
+        sync_spawns(&mut world);
+        world.time += 1;
+    }
+
+    fn parts(&self, creep: &str, part: Part) -> u32 {
+        self.body(creep).iter().filter(|p| **p == part).count() as u32
+    }
+
+    fn act(&self, world: &mut World, intent: Intent) -> Option<()> {
+        match intent {
+            Intent::Harvest { creep, target } => {
+                let power = self.parts(&creep, Part::Work) * HARVEST_POWER;
+                let source = world.sources.get_mut(&target)?;
+                let amount = power.min(source.energy);
+                source.energy -= amount;
+                let creep = world.creeps.get_mut(&creep)?;
+                let overflow = amount - give(creep, amount);
+                let pos = creep.pos;
+                drop_energy(world, pos, overflow);
+            }
+            Intent::Build { creep, target } => {
+                let power = self.parts(&creep, Part::Work) * BUILD_POWER;
+                let creep = world.creeps.get_mut(&creep)?;
+                let site = world.sites.get_mut(&target)?;
+                let amount = take(creep, power.min(site.progress_total - site.progress));
+                site.progress += amount;
+                if site.progress >= site.progress_total {
+                    let site = world.sites.remove(&target)?;
+                    build_structure(world, site.pos, site.structure_type);
+                }
+            }
+            Intent::UpgradeController { creep, target } => {
+                let power = self.parts(&creep, Part::Work) * UPGRADE_CONTROLLER_POWER;
+                let creep = world.creeps.get_mut(&creep)?;
+                let controller = world.controllers.get_mut(&target)?;
+                // nothing to upgrade at max level
+                controller_levels(controller.level)?;
+                controller.progress += take(creep, power);
+                while let Some(total) = controller_levels(controller.level) {
+                    if controller.progress < total {
+                        break;
+                    }
+                    controller.progress -= total;
+                    controller.level += 1;
+                    controller.progress_total = controller_levels(controller.level).unwrap_or(0);
+                }
+            }
+            Intent::Transfer { creep, target } => {
+                let creep = world.creeps.get_mut(&creep)?;
+                let structure = world.structures.get_mut(&target)?;
+                let free = structure.free_energy_capacity()?;
+                let store = structure.store.as_mut()?;
+                let amount = take(creep, free);
+                store.energy += amount;
+                store.used += amount;
+            }
+            Intent::Withdraw { creep, target } => {
+                let creep = world.creeps.get_mut(&creep)?;
+                let free = creep.carry_capacity - creep.carry_total;
+                if let Some(structure) = world.structures.get_mut(&target) {
+                    let store = structure.store.as_mut()?;
+                    let amount = give(creep, free.min(store.energy));
+                    store.energy -= amount;
+                    store.used -= amount;
+                } else {
+                    let pile = world.piles.get_mut(&target)?;
+                    pile.amount -= give(creep, free.min(pile.amount));
+                }
+            }
+            Intent::Pickup { creep, target } => {
+                let creep = world.creeps.get_mut(&creep)?;
+                let pile = world.piles.get_mut(&target)?;
+                pile.amount -= give(creep, pile.amount);
+            }
+            Intent::Repair { creep, target } => {
+                let work = self.parts(&creep, Part::Work);
+                let creep = world.creeps.get_mut(&creep)?;
+                let structure = world.structures.get_mut(&target)?;
+                let missing = structure.hits_max - structure.hits;
+                let spent = take(creep, work.min(creep.energy));
+                structure.hits += (spent * REPAIR_POWER).min(missing);
+            }
+            Intent::Dismantle { creep, target } => {
+                let power = self.parts(&creep, Part::Work) * DISMANTLE_POWER;
+                let structure = world.structures.get_mut(&target)?;
+                structure.hits = structure.hits.saturating_sub(power);
+                if structure.hits == 0 {
+                    world.structures.remove(&target);
+                }
+            }
+            Intent::Move { .. } | Intent::Step { .. } | Intent::Spawn { .. } => {}
+        }
+        Some(())
+    }
+
+    fn spawn(&mut self, world: &mut World, spawn: &str, name: String, body: Vec<Part>) {
+        let (pos, busy) = match world.spawns.get(spawn) {
+            Some(spawn) => (spawn.pos, spawn.spawning.is_some()),
+            None => return,
+        };
+        let cost = body_cost(&body);
+        if busy || world.creeps.contains_key(&name) || room_energy(world, pos.room_name()).0 < cost
+        {
+            return;
+        }
+        spend_room_energy(world, pos.room_name(), cost);
+
+        let spawn_time = body.len() as u32 * CREEP_SPAWN_TIME;
+        let carry_capacity = body.iter().filter(|p| **p == Part::Carry).count() as u32;
+        world.spawns.get_mut(spawn).unwrap().spawning = Some(SpawningCreep {
+            name: name.clone(),
+            need_time: spawn_time,
+            remaining_time: spawn_time,
+            directions: vec![],
+        });
+        world.creeps.insert(
+            name.clone(),
+            CreepState {
+                name: name.clone(),
+                pos,
+                spawning: true,
+                carry_total: 0,
+                carry_capacity: carry_capacity * CARRY_CAPACITY,
+                energy: 0,
+                fatigue: 0,
+                ticks_to_live: None,
+            },
+        );
+        self.bodies.insert(name, body);
+    }
+
+    fn finish_spawning(&mut self, world: &mut World) {
+        let spawns: Vec<String> = world.spawns.keys().cloned().collect();
+        for name in spawns {
+            let spawning = match world.spawns.get_mut(&name).unwrap().spawning.as_mut() {
+                Some(spawning) => spawning,
+                None => continue,
+            };
+            spawning.remaining_time = spawning.remaining_time.saturating_sub(1);
+            if spawning.remaining_time > 0 {
+                continue;
+            }
+            let spawn = world.spawns.get_mut(&name).unwrap();
+            let creep_name = spawn.spawning.take().unwrap().name;
+            let spawn_pos = spawn.pos;
+
+            let occupied = occupied_tiles(world);
+            let blocked = blocked_tiles(world);
+            let exit = NEIGHBOURS
+                .iter()
+                .filter_map(|offset| step(spawn_pos, *offset))
+                .find(|pos| !blocked.contains(pos) && !occupied.contains(pos));
+            let life_time = if self.parts(&creep_name, Part::Claim) > 0 {
+                CREEP_CLAIM_LIFE_TIME
+            } else {
+                CREEP_LIFE_TIME
+            };
+            if let Some(creep) = world.creeps.get_mut(&creep_name) {
+                creep.spawning = false;
+                creep.ticks_to_live = Some(life_time);
+                // a spawn boxed in completely keeps the creep on top of itself
+                creep.pos = exit.unwrap_or(spawn_pos);
+            }
+        }
+    }
+
+    fn age_creeps(&mut self, world: &mut World) {
+        let mut dead = vec![];
+        for creep in world.creeps.values_mut() {
+            if let Some(ticks_to_live) = creep.ticks_to_live.as_mut() {
+                *ticks_to_live -= 1;
+                if *ticks_to_live == 0 {
+                    dead.push(creep.name.clone());
+                }
+            }
+        }
+        for name in dead {
+            let creep = world.creeps.remove(&name).unwrap();
+            self.bodies.remove(&name);
+            let id = world.next_id();
+            world.piles.insert(
+                id.clone(),
+                PileState {
+                    id,
+                    kind: PileKind::Tombstone,
+                    pos: creep.pos,
+                    amount: creep.energy,
+                },
+            );
+        }
+    }
+
+    /// Makes steps the way the game does: creeps stepping into each other's tiles swap, and
+    /// steps onto a tile whose creep stays where it is fail.
+    fn step_creeps(&self, world: &mut World, steps: BTreeMap<String, Position>) {
+        let blocked = blocked_tiles(world);
+        let mut steps: BTreeMap<String, Position> = steps
+            .into_iter()
+            .filter(|(name, target)| match world.creeps.get(name) {
+                Some(creep) => {
+                    !creep.spawning
+                        && self.parts(name, Part::Move) > 0
+                        && creep.pos.room_name() == target.room_name()
+                        && !blocked.contains(target)
+                }
+                None => false,
+            })
+            .collect();
+        // the first creep to ask gets the tile
+        let mut taken = HashSet::new();
+        steps.retain(|_, target| taken.insert(*target));
+        loop {
+            let staying: HashSet<Position> = world
+                .creeps
+                .iter()
+                .filter(|(name, creep)| !creep.spawning && !steps.contains_key(*name))
+                .map(|(_, creep)| creep.pos)
+                .collect();
+            let before = steps.len();
+            steps.retain(|_, target| !staying.contains(target));
+            if steps.len() == before {
+                break;
+            }
+        }
+        for (name, target) in steps {
+            world.creeps.get_mut(&name).unwrap().pos = target;
+        }
+    }
+
+    fn move_creeps(&self, world: &mut World, moves: BTreeMap<String, Position>) {
+        let blocked = blocked_tiles(world);
+        let mut occupied = occupied_tiles(world);
+        for (name, target) in moves {
+            let from = match world.creeps.get(&name) {
+                Some(creep) if !creep.spawning && self.parts(&name, Part::Move) > 0 => creep.pos,
+                _ => continue,
+            };
+            if let Some(next) = next_step(from, target, &blocked, &occupied) {
+                occupied.remove(&from);
+                occupied.insert(next);
+                world.creeps.get_mut(&name).unwrap().pos = next;
+            }
+        }
+    }
+}
+
+/// Adds up to `amount` energy to `creep`, returning how much fit.
+fn give(creep: &mut CreepState, amount: u32) -> u32 {
+    let amount = amount.min(creep.carry_capacity - creep.carry_total);
+    creep.energy += amount;
+    creep.carry_total += amount;
+    amount
+}
+
+/// Takes up to `amount` energy from `creep`, returning how much it had.
+fn take(creep: &mut CreepState, amount: u32) -> u32 {
+    let amount = amount.min(creep.energy);
+    creep.energy -= amount;
+    creep.carry_total -= amount;
+    amount
+}
+
+fn drop_energy(world: &mut World, pos: Position, mut amount: u32) {
+    // a container on the tile catches what it has room for
+    let container = world.structures.values_mut().find(|structure| {
+        structure.pos == pos && structure.structure_type == StructureType::Container
+    });
+    if let Some(store) = container.and_then(|container| container.store.as_mut()) {
+        let stored = amount.min(store.capacity - store.used);
+        store.energy += stored;
+        store.used += stored;
+        amount -= stored;
+    }
+    if amount == 0 {
+        return;
+    }
+    let existing = world
+        .piles
+        .values_mut()
+        .find(|pile| pile.pos == pos && pile.kind == PileKind::Dropped);
+    match existing {
+        Some(pile) => pile.amount += amount,
+        None => {
+            world.add_pile(pos, PileKind::Dropped, amount);
+        }
+    }
+}
+
+fn build_structure(world: &mut World, pos: Position, structure_type: StructureType) {
+    let (hits, capacity) = match structure_type {
+        StructureType::Road => (5000, None),
+        StructureType::Extension => (1000, Some(50)),
+        StructureType::Container => (250_000, Some(2000)),
+        StructureType::Storage => (10_000, Some(1_000_000)),
+        StructureType::Tower => (3000, Some(1000)),
+        StructureType::Link => (1000, Some(800)),
+        StructureType::Wall | StructureType::Rampart => (1, None),
+        _ => (1000, None),
+    };
+    let structure = world.add_structure(pos, structure_type);
+    structure.hits = hits;
+    structure.hits_max = hits;
+    if let Some(capacity) = capacity {
+        structure.store = Some(Store {
+            energy: 0,
+            used: 0,
+            capacity,
+        });
+        structure.transferable = true;
+        structure.withdrawable = structure_type != StructureType::Extension;
+    }
+    if structure_type == StructureType::Container || structure_type == StructureType::Road {
+        structure.owner = Owner::Nobody;
+    }
+}
+
+fn is_spawn_energy(structure: &StructureState, room: RoomName) -> bool {
+    structure.owner == Owner::Mine
+        && structure.pos.room_name() == room
+        && matches!(
+            structure.structure_type,
+            StructureType::Spawn | StructureType::Extension
+        )
+}
+
+/// `(energy, capacity)` available for spawning in `room`.
+fn room_energy(world: &World, room: RoomName) -> (u32, u32) {
+    world
+        .structures
+        .values()
+        .filter(|structure| is_spawn_energy(structure, room))
+        .filter_map(|structure| structure.store)
+        .fold((0, 0), |(energy, capacity), store| {
+            (energy + store.energy, capacity + store.capacity)
+        })
+}
+
+/// Takes `amount` out of the spawns and extensions of `room`, spawns first.
+fn spend_room_energy(world: &mut World, room: RoomName, mut amount: u32) {
+    let mut structures: Vec<&mut StructureState> = world
+        .structures
+        .values_mut()
+        .filter(|structure| is_spawn_energy(structure, room))
+        .collect();
+    structures.sort_by_key(|structure| structure.structure_type != StructureType::Spawn);
+    for structure in structures {
+        if let Some(store) = structure.store.as_mut() {
+            let spent = amount.min(store.energy);
+            store.energy -= spent;
+            store.used -= spent;
+            amount -= spent;
+        }
+    }
+}
+
+fn regenerate(world: &mut World) {
+    for source in world.sources.values_mut() {
+        source.ticks_to_regeneration = source.ticks_to_regeneration.saturating_sub(1);
+        if source.ticks_to_regeneration == 0 {
+            source.energy = source.energy_capacity;
+            source.ticks_to_regeneration = ENERGY_REGEN_TIME;
+        }
+    }
+
+    let spawns: Vec<(String, RoomName)> = world
+        .spawns
+        .values()
+        .map(|spawn| (spawn.id.clone(), spawn.pos.room_name()))
+        .collect();
+    for (id, room) in spawns {
+        if room_energy(world, room).0 >= SPAWN_ENERGY_CAPACITY {
+            continue;
+        }
+        if let Some(store) = world.structures.get_mut(&id).and_then(|s| s.store.as_mut()) {
+            if store.energy < store.capacity {
+                store.energy += 1;
+                store.used += 1;
+            }
+        }
+    }
+}
+
+/// Copies energy from the spawn structures into the spawn states.
+fn sync_spawns(world: &mut World) {
+    let mut rooms: HashMap<RoomName, (u32, u32)> = HashMap::new();
+    let states: Vec<(String, Option<Store>, RoomName)> = world
+        .spawns
+        .values()
+        .map(|spawn| {
+            let store = world.structures.get(&spawn.id).and_then(|s| s.store);
+            (spawn.name.clone(), store, spawn.pos.room_name())
+        })
+        .collect();
+    for (name, store, room) in states {
+        let (room_energy, room_energy_capacity) = *rooms
+            .entry(room)
+            .or_insert_with(|| room_energy(world, room));
+        let spawn = world.spawns.get_mut(&name).unwrap();
+        if let Some(store) = store {
+            spawn.energy = store.energy;
+            spawn.energy_capacity = store.capacity;
+        }
+        spawn.room_energy = room_energy;
+        spawn.room_energy_capacity = room_energy_capacity;
+    }
+}
+
+fn step(pos: Position, (dx, dy): (i32, i32)) -> Option<Position> {
+    let (x, y) = pos.coords_signed();
+    let (x, y) = (x + dx, y + dy);
+    if x < 0 || y < 0 || x > 49 || y > 49 {
+        return None;
+    }
+    Some(Position::new(x as u32, y as u32, pos.room_name()))
+}
+
+/// Tiles no creep can stand on: walls, sources, controllers and solid structures.
+fn blocked_tiles(world: &World) -> HashSet<Position> {
+    let walls = world
+        .terrain
+        .iter()
+        .filter(|(_, terrain)| **terrain == Terrain::Wall)
+        .map(|(pos, _)| *pos);
+    let structures = world
+        .structures
+        .values()
+        .filter(|structure| {
+            !matches!(
+                structure.structure_type,
+                StructureType::Road | StructureType::Container | StructureType::Rampart
+            )
+        })
+        .map(|structure| structure.pos);
+    walls
+        .chain(structures)
+        .chain(world.sources.values().map(|source| source.pos))
+        .chain(world.controllers.values().map(|controller| controller.pos))
+        .collect()
+}
+
+fn occupied_tiles(world: &World) -> HashSet<Position> {
+    world
+        .creeps
+        .values()
+        .filter(|creep| !creep.spawning)
+        .map(|creep| creep.pos)
+        .collect()
+}
+
+/// First step of the shortest path from `from` onto `target`, or to a free tile next to it if
+/// `target` can't be stood on.
+fn next_step(
+    from: Position,
+    target: Position,
+    blocked: &HashSet<Position>,
+    occupied: &HashSet<Position>,
+) -> Option<Position> {
+    let range = if blocked.contains(&target) { 1 } else { 0 };
+    let is_goal = |pos: Position| pos.get_range_to(&target) <= range;
+    if from.room_name() != target.room_name() || is_goal(from) {
+        return None;
+    }
+    let mut first_steps: HashMap<Position, Position> = HashMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(from);
+    first_steps.insert(from, from);
+    while let Some(pos) = queue.pop_front() {
+        for offset in NEIGHBOURS.iter() {
+            let next = match step(pos, *offset) {
+                Some(next) => next,
+                None => continue,
+            };
+            if first_steps.contains_key(&next)
+                || blocked.contains(&next)
+                || occupied.contains(&next)
+            {
+                continue;
+            }
+            let first = if pos == from { next } else { first_steps[&pos] };
+            if is_goal(next) {
+                return Some(first);
+            }
+            first_steps.insert(next, first);
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{pos, room_name};
+    use crate::api::GameApi;
+    use crate::data::{Creep, Role, Room};
+    use crate::tasks::{Fallback, Harvest, Task, Transfer, Upgrade};
+
+    struct Worker;
+
+    impl Role for Worker {
+        fn name(&self) -> &str {
+            "Worker"
+        }
+
+        fn body(&self, _energy: u32) -> Vec<Part> {
+            vec![Part::Work, Part::Carry, Part::Move, Part::Move]
+        }
+
+        fn population(&self) -> usize {
+            3
+        }
+
+        fn next_task(&self, creep: &Creep, _rooms: &HashMap<RoomName, Room>) -> Option<Task> {
+            if creep.energy() == 0 {
+                Some(Harvest::default().into())
+            } else {
+                Some(
+                    Fallback::new(vec![Transfer::default().into(), Upgrade::default().into()])
+                        .into(),
+                )
+            }
+        }
+    }
+
+    #[test]
+    fn spawning_and_harvesting_take_their_time() {
+        let mut world = World::new();
+        world.add_spawn("Spawn1", pos(25, 25));
+        world.add_source(pos(25, 20)).energy = 10;
+        let mut sim = Simulator::new(world);
+
+        let api = sim.api.clone();
+        let body = vec![Part::Work, Part::Carry, Part::Move];
+        assert_eq!(
+            api.spawn_creep("Spawn1", &body, "Harvester:1", &[]),
+            screeps::ReturnCode::Ok
+        );
+        for _ in 0..8 {
+            sim.step();
+        }
+        assert!(sim.world().creeps["Harvester:1"].spawning);
+        sim.step();
+        let creep = sim.world().creeps["Harvester:1"].clone();
+        assert!(!creep.spawning);
+        assert_eq!(creep.ticks_to_live, Some(CREEP_LIFE_TIME));
+        // 200 went into the creep, the spawn refilled itself by one every tick since
+        assert_eq!(sim.world().spawns["Spawn1"].energy, 100 + 9);
+
+        let source = sim.world().sources.keys().next().unwrap().clone();
+        while api.harvest("Harvester:1", &source) != screeps::ReturnCode::Ok {
+            api.move_to("Harvester:1", pos(25, 20));
+            sim.step();
+        }
+        sim.step();
+        assert_eq!(sim.world().creeps["Harvester:1"].energy, 2);
+        assert_eq!(sim.world().sources[&source].energy, 8);
+    }
+
+    #[test]
+    fn workers_keep_their_numbers_up_and_level_the_controller() {
+        let mut world = World::new();
+        world.add_spawn("Spawn1", pos(25, 25));
+        world.add_source(pos(20, 15));
+        world.add_source(pos(32, 18));
+        world.add_controller(pos(25, 35));
+        let mut sim = Simulator::new(world);
+        let mut game = Game::new();
+        game.register_role(Worker);
+
+        // nothing but the spawn queue, fed by the worker role, spawns them
+        sim.run(&mut game, 1, &|_| {});
+        let room = room_name();
+        assert_eq!(game.spawn_queues[&room].len(), 2);
+        assert_eq!(sim.world().creeps.len(), 1);
+        sim.run(&mut game, 1, &|_| {});
+        assert_eq!(game.spawn_queues[&room].len(), 2);
+
+        sim.run(&mut game, 3000, &|_| {});
+
+        let world = sim.world();
+        let controller = world.controllers.values().next().unwrap();
+        assert!(controller.level >= 2, "controller at {:?}", controller);
+        let workers = world.creeps.values().filter(|c| !c.spawning).count();
+        assert!(workers >= 3, "only {} workers", workers);
+    }
+}
@@ -0,0 +1,137 @@
+use screeps::Part;
+
+/// Creeps can't have more parts than this.
+pub const MAX_BODY_PARTS: usize = 50;
+
+pub fn body_cost(body: &[Part]) -> u32 {
+    body.iter().map(|part| part.cost()).sum()
+}
+
+/// Builds creep bodies by repeating a template as often as the energy budget allows,
+/// e.g. `[Work, Carry, Move]` for a worker, between optional fixed prefix and suffix parts.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BodyBuilder {
+    prefix: Vec<Part>,
+    template: Vec<Part>,
+    suffix: Vec<Part>,
+    max_repeats: Option<usize>,
+    tough_first: bool,
+    move_last: bool,
+}
+
+impl BodyBuilder {
+    pub fn new(template: &[Part]) -> BodyBuilder {
+        BodyBuilder {
+            template: template.to_vec(),
+            ..BodyBuilder::default()
+        }
+    }
+
+    /// Parts every body starts with, regardless of the budget.
+    pub fn prefix(mut self, parts: &[Part]) -> BodyBuilder {
+        self.prefix = parts.to_vec();
+        self
+    }
+
+    /// Parts every body ends with, regardless of the budget.
+    pub fn suffix(mut self, parts: &[Part]) -> BodyBuilder {
+        self.suffix = parts.to_vec();
+        self
+    }
+
+    pub fn max_repeats(mut self, repeats: usize) -> BodyBuilder {
+        self.max_repeats = Some(repeats);
+        self
+    }
+
+    /// Moves all `Tough` parts to the front, so they soak up damage first.
+    pub fn tough_first(mut self) -> BodyBuilder {
+        self.tough_first = true;
+        self
+    }
+
+    /// Moves all `Move` parts to the back, so the creep stays mobile the longest.
+    pub fn move_last(mut self) -> BodyBuilder {
+        self.move_last = true;
+        self
+    }
+
+    /// How often the template fits into `energy` next to the fixed parts.
+    pub fn repeats(&self, energy: u32) -> usize {
+        let fixed_cost = body_cost(&self.prefix) + body_cost(&self.suffix);
+        let fixed_len = self.prefix.len() + self.suffix.len();
+        let template_cost = body_cost(&self.template);
+        if self.template.is_empty() || fixed_cost > energy || fixed_len > MAX_BODY_PARTS {
+            return 0;
+        }
+
+        let by_energy = ((energy - fixed_cost) / template_cost) as usize;
+        let by_size = (MAX_BODY_PARTS - fixed_len) / self.template.len();
+        let repeats = by_energy.min(by_size);
+        self.max_repeats.map_or(repeats, |max| repeats.min(max))
+    }
+
+    /// The biggest body `energy` pays for, `None` if not even the fixed parts fit.
+    pub fn build(&self, energy: u32) -> Option<Vec<Part>> {
+        let fixed_cost = body_cost(&self.prefix) + body_cost(&self.suffix);
+        if fixed_cost > energy || self.prefix.len() + self.suffix.len() > MAX_BODY_PARTS {
+            return None;
+        }
+
+        let mut body = self.prefix.clone();
+        for _ in 0..self.repeats(energy) {
+            body.extend_from_slice(&self.template);
+        }
+        body.extend_from_slice(&self.suffix);
+        if body.is_empty() {
+            return None;
+        }
+
+        if self.tough_first {
+            body.sort_by_key(|part| *part != Part::Tough);
+        }
+        if self.move_last {
+            body.sort_by_key(|part| *part == Part::Move);
+        }
+        Some(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use screeps::Part::*;
+
+    #[test]
+    fn template_is_repeated_within_budget() {
+        let builder = BodyBuilder::new(&[Work, Carry, Move]);
+        assert_eq!(builder.build(199), None);
+        assert_eq!(builder.build(200), Some(vec![Work, Carry, Move]));
+        assert_eq!(builder.build(550).unwrap().len(), 6);
+        assert_eq!(builder.clone().max_repeats(1).build(550).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn body_never_exceeds_fifty_parts() {
+        let body = BodyBuilder::new(&[Carry, Move])
+            .prefix(&[Work])
+            .build(100_000)
+            .unwrap();
+        assert_eq!(body.len(), 49);
+        assert_eq!(body[0], Work);
+        assert!(BodyBuilder::new(&[Move]).build(100_000).unwrap().len() == MAX_BODY_PARTS);
+    }
+
+    #[test]
+    fn fixed_parts_and_ordering() {
+        let body = BodyBuilder::new(&[Attack, Move, Tough])
+            .suffix(&[Heal])
+            .tough_first()
+            .move_last()
+            .build(250 + 2 * 140)
+            .unwrap();
+        assert_eq!(body, vec![Tough, Tough, Attack, Attack, Heal, Move, Move]);
+        assert_eq!(body_cost(&body), 250 + 2 * 140);
+        assert_eq!(BodyBuilder::new(&[Work]).suffix(&[Heal]).build(200), None);
+    }
+}
@@ -1,107 +1,144 @@
+use crate::api::{api, CreepState};
+use crate::data::Roles;
 use crate::tasks::Task;
+use crate::travel::{travel, TravelState};
 use core::borrow::Borrow;
-use screeps::memory::MemoryReference;
-use screeps::HasPosition;
-use screeps::ReturnCode;
-use screeps::Room;
-use screeps::RoomObjectProperties;
-use screeps::RoomPosition;
-use screeps::Source;
-use screeps::StructureController;
-use screeps::{ConstructionSite, ResourceType, Transferable};
+use screeps::{Direction, HasPosition, Position, ReturnCode, RoomName};
+use std::cell::{Cell, RefCell};
 use std::fmt;
-use stdweb::js;
 
 pub struct Creep {
-    _source: screeps::Creep,
     name: String,
     spawning: bool,
     carry_total: u32,
     carry_capacity: u32,
+    energy: u32,
+    fatigue: u32,
     ticks_to_live: u32,
-    pos: RoomPosition,
-    room: Room,
+    pos: Position,
+    room_id: RoomName,
     job: Job,
     tasks: Vec<Task>,
+    task_started: bool,
+    task_failures: u32,
+    tasks_dirty: bool,
+    packed_tasks: Option<String>,
+    // tasks only get to see `&Creep`, so moving updates this from behind a shared reference
+    travel: RefCell<Option<TravelState>>,
+    packed_travel: Option<String>,
+    // this tick's step, handed to `travel::resolve` once all tasks ran
+    next_move: Cell<Option<Direction>>,
+    pinned: Cell<bool>,
 }
 
 impl Creep {
-    pub fn from(creep: screeps::Creep) -> Creep {
-        let _source = creep.borrow();
-        let name: String = _source.name();
-        let job_name: String = name.split(':').next().expect("invalid name!").to_string();
-        let spawning = _source.spawning();
-        let carry_total = _source.carry_total();
-        let carry_capacity = _source.carry_capacity();
-        let ticks_to_live: u32 = if !spawning {
-            _source.ticks_to_live()
-        } else {
-            1500
-        };
-        let pos = _source.pos();
-        let room = _source.room();
+    pub fn from(creep: CreepState, roles: &Roles) -> Creep {
+        let name = creep.name;
+        let job = roles.job_for(&name);
+        let ticks_to_live = creep.ticks_to_live.unwrap_or(1500);
+        // the heap copy is authoritative from here on, memory only seeds it after a reset
+        let packed_tasks = api().creep_memory(&name, "tasks");
+        let tasks = packed_tasks
+            .as_ref()
+            .and_then(|packed| Creep::unpack_tasks(packed))
+            .unwrap_or_default();
+        let packed_travel = api().creep_memory(&name, "_trav");
+        let travel = packed_travel
+            .as_ref()
+            .and_then(|packed| serde_json::from_str(packed).ok());
         Creep {
             name,
-            spawning,
-            carry_total,
-            carry_capacity,
+            spawning: creep.spawning,
+            carry_total: creep.carry_total,
+            carry_capacity: creep.carry_capacity,
+            energy: creep.energy,
+            fatigue: creep.fatigue,
             ticks_to_live,
-            pos,
-            room,
-            job: Job::from_string(job_name),
-            tasks: vec![],
-            _source: creep,
+            pos: creep.pos,
+            room_id: creep.pos.room_name(),
+            job,
+            tasks,
+            task_started: false,
+            task_failures: 0,
+            tasks_dirty: false,
+            packed_tasks,
+            travel: RefCell::new(travel),
+            packed_travel,
+            next_move: Cell::new(None),
+            pinned: Cell::new(false),
         }
     }
 
-    fn unpack_tasks(&self, creep: &screeps::Creep) -> Option<Vec<Task>> {
-        let _packed_tasks = creep.memory().string("tasks");
-        if _packed_tasks.is_err() {
-            error!("could not unpack!");
+    fn unpack_tasks(tasks: &str) -> Option<Vec<Task>> {
+        match serde_json::from_str(tasks) {
+            Ok(unpacked) => Some(unpacked),
+            Err(error) => {
+                error!("could not deserialize: '{}' with {:?}", tasks, error);
+                None
+            }
         }
-        let packed_tasks = _packed_tasks.unwrap();
-        if packed_tasks.is_none() {
-            return None;
+    }
+
+    /// Writes the task queue back into `Memory.creeps[name].tasks` if it changed this tick.
+    pub fn flush_tasks(&mut self) {
+        if !self.tasks_dirty {
+            return;
         }
-        let tasks = packed_tasks.unwrap();
-        let unpacked: Result<Vec<Task>, serde_json::error::Error> = serde_json::from_str(&tasks);
-        if unpacked.is_err() {
-            error!(
-                "could not deserialize: '{}' with {:?}",
-                tasks,
-                unpacked.unwrap_err()
-            );
-            return None;
+        self.tasks_dirty = false;
+
+        let packed = if self.tasks.is_empty() {
+            None
+        } else {
+            match serde_json::to_string(&self.tasks) {
+                Ok(packed) => Some(packed),
+                Err(error) => {
+                    error!("could not serialize tasks of {}: {:?}", self.name, error);
+                    return;
+                }
+            }
+        };
+        if packed == self.packed_tasks {
+            return;
         }
-        return Some(unpacked.unwrap());
+
+        api().set_creep_memory(&self.name, "tasks", packed.as_deref());
+        self.packed_tasks = packed;
     }
 
-    pub fn refresh(&mut self, creep: screeps::Creep) {
-        let _source = creep.borrow();
-        self.spawning = _source.spawning();
-        self.carry_total = _source.carry_total();
-        self.carry_capacity = _source.carry_capacity();
-        if !self.spawning {
-            // this will panic otherwise
-            self.ticks_to_live = _source.ticks_to_live();
-        }
-        self.pos = _source.pos();
-        self.room = _source.room();
-        if let Some(tasks) = self.unpack_tasks(_source) {
-            self.tasks = tasks;
+    /// Writes where the creep is travelling into `Memory.creeps[name]._trav` if it changed.
+    pub fn flush_travel(&mut self) {
+        let packed = match self.travel.get_mut() {
+            Some(travel) => match serde_json::to_string(travel) {
+                Ok(packed) => Some(packed),
+                Err(error) => {
+                    error!("could not serialize path of {}: {:?}", self.name, error);
+                    return;
+                }
+            },
+            None => None,
+        };
+        if packed == self.packed_travel {
+            return;
         }
-        self._source = creep;
-        self.show_creep_circle();
+
+        api().set_creep_memory(&self.name, "_trav", packed.as_deref());
+        self.packed_travel = packed;
     }
 
-    fn show_creep_circle(&self) {
-        let room = self.room.name();
-        let x = self.pos.x();
-        let y = self.pos.y();
-        js! {
-            console.addVisual(@{room}, {t: 'c', x: @{x}, y: @{y},
-            s: {radius: 0.55, fill: "transparent", stroke: "red"}});
+    pub fn refresh(&mut self, creep: CreepState) {
+        self.spawning = creep.spawning;
+        self.carry_total = creep.carry_total;
+        self.carry_capacity = creep.carry_capacity;
+        self.energy = creep.energy;
+        self.fatigue = creep.fatigue;
+        if let Some(ticks_to_live) = creep.ticks_to_live {
+            self.ticks_to_live = ticks_to_live;
         }
+        self.pos = creep.pos;
+        self.room_id = creep.pos.room_name();
+        self.next_move.set(None);
+        self.pinned.set(false);
+        api().draw_circle(self.pos);
     }
 
     pub fn name(&self) -> &str {
@@ -112,10 +149,6 @@ impl Creep {
         self.spawning
     }
 
-    pub fn memory(&self) -> MemoryReference {
-        self._source.memory()
-    }
-
     pub fn carry_total(&self) -> u32 {
         self.carry_total
     }
@@ -124,72 +157,172 @@ impl Creep {
         self.carry_capacity
     }
 
+    pub fn energy(&self) -> u32 {
+        self.energy
+    }
+
+    pub fn fatigue(&self) -> u32 {
+        self.fatigue
+    }
+
     pub fn ticks_to_live(&self) -> u32 {
         self.ticks_to_live
     }
 
-    pub fn pos(&self) -> &RoomPosition {
+    pub fn pos(&self) -> &Position {
         self.pos.borrow()
     }
 
-    pub fn room(&self) -> &Room {
-        self.room.borrow()
+    pub fn room_id(&self) -> &RoomName {
+        self.room_id.borrow()
     }
 
+    /// Moves onto `target`, or next to it if it can't be stood on, see `travel`.
     pub fn move_to<T: ?Sized + HasPosition>(&self, target: &T) -> ReturnCode {
-        self._source.move_to(target)
+        self.move_to_range(target, 0)
+    }
+
+    pub fn move_to_range<T: ?Sized + HasPosition>(&self, target: &T, range: u32) -> ReturnCode {
+        travel(self, &mut self.travel.borrow_mut(), target.pos(), range)
+    }
+
+    pub fn travel_state(&self) -> Option<TravelState> {
+        self.travel.borrow().clone()
+    }
+
+    /// The step the creep wants to take this tick, see `travel::resolve`.
+    pub fn next_move(&self) -> Option<Direction> {
+        self.next_move.get()
+    }
+
+    pub fn request_move(&self, direction: Direction) {
+        self.next_move.set(Some(direction));
+    }
+
+    /// Keeps the creep where it is for this tick, other creeps have to go around it instead of
+    /// shoving it out of their way.
+    pub fn pin(&self) {
+        self.pinned.set(true);
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.pinned.get()
+    }
+
+    pub fn harvest(&self, source_id: &str) -> ReturnCode {
+        api().harvest(&self.name, source_id)
+    }
+
+    pub fn build(&self, site_id: &str) -> ReturnCode {
+        api().build(&self.name, site_id)
+    }
+
+    pub fn upgrade_controller(&self, controller_id: &str) -> ReturnCode {
+        api().upgrade_controller(&self.name, controller_id)
+    }
+
+    pub fn transfer_all_energy(&self, target_id: &str) -> ReturnCode {
+        api().transfer_energy(&self.name, target_id)
     }
 
-    pub fn harvest(&self, source: &Source) -> ReturnCode {
-        self._source.harvest(source)
+    /// Withdraws from structures, tombstones and ruins.
+    pub fn withdraw_all_energy(&self, target_id: &str) -> ReturnCode {
+        api().withdraw_energy(&self.name, target_id)
     }
 
-    pub fn build(&self, construction_site: &ConstructionSite) -> ReturnCode {
-        self._source.build(construction_site)
+    pub fn repair(&self, target_id: &str) -> ReturnCode {
+        api().repair(&self.name, target_id)
     }
 
-    pub fn upgrade_controller(&self, controller: &StructureController) -> ReturnCode {
-        self._source.upgrade_controller(controller)
+    pub fn dismantle(&self, target_id: &str) -> ReturnCode {
+        api().dismantle(&self.name, target_id)
     }
 
-    pub fn transfer_all_energy<T: ?Sized + Transferable>(&self, target: &T) -> ReturnCode {
-        self._source.transfer_all(target, ResourceType::Energy)
+    pub fn pickup(&self, resource_id: &str) -> ReturnCode {
+        api().pickup(&self.name, resource_id)
     }
 
     pub fn job(&self) -> &Job {
         self.job.borrow()
     }
+
+    pub fn tasks(&self) -> &[Task] {
+        self.tasks.borrow()
+    }
+
+    pub fn push_task(&mut self, task: Task) {
+        self.tasks.push(task);
+        self.tasks_dirty = true;
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub(crate) fn set_job(&mut self, job: Job) {
+        self.job = job;
+    }
+
+    pub(crate) fn take_tasks(&mut self) -> Vec<Task> {
+        std::mem::take(&mut self.tasks)
+    }
+
+    pub(crate) fn set_tasks(&mut self, tasks: Vec<Task>) {
+        self.tasks = tasks;
+    }
+
+    pub(crate) fn mark_tasks_dirty(&mut self) {
+        self.tasks_dirty = true;
+    }
+
+    pub(crate) fn task_started(&self) -> bool {
+        self.task_started
+    }
+
+    pub(crate) fn set_task_started(&mut self, started: bool) {
+        self.task_started = started;
+    }
+
+    pub(crate) fn task_failures(&self) -> u32 {
+        self.task_failures
+    }
+
+    pub(crate) fn set_task_failures(&mut self, failures: u32) {
+        self.task_failures = failures;
+    }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Job {
-    Upgrader,
-    Starter,
-    Builder,
-    Unassigned,
+/// The role a creep was spawned for, taken from the `<role>:` prefix of its name.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Job {
+    name: String,
 }
 
+const UNASSIGNED: &str = "Unassigned";
+
 impl Job {
-    fn from_string(job: String) -> Job {
-        match job.as_str() {
-            "Upgrader" => Job::Upgrader,
-            "Starter" => Job::Starter,
-            "Builder" => Job::Builder,
-            _ => Job::Unassigned,
+    pub fn new(name: &str) -> Job {
+        Job {
+            name: name.to_string(),
         }
     }
+
+    /// Job of creeps whose name doesn't start with the name of a registered role.
+    pub fn unassigned() -> Job {
+        Job::new(UNASSIGNED)
+    }
+
+    pub fn is_unassigned(&self) -> bool {
+        self.name == UNASSIGNED
+    }
+
     pub fn as_str(&self) -> &str {
-        match self {
-            Job::Upgrader => "Upgrader",
-            Job::Starter => "Starter",
-            Job::Builder => "Builder",
-            Job::Unassigned => "Unassigned",
-        }
+        self.name.borrow()
     }
 }
 
 impl fmt::Display for Job {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{}", self.name)
     }
 }
@@ -1,5 +1,13 @@
-use crate::data::{Creep, Job, Spawn};
-use std::collections::HashMap;
+use crate::api::api;
+use crate::data::{
+    Creep, Job, Logistics, Reservations, Role, Roles, Room, Spawn, SpawnQueue, SpawnRequest,
+};
+use crate::logging::{with_context, LogContext};
+use crate::roles::Hauler;
+use crate::tasks::{run_creep_tasks, IdleHook, Task, TaskContext, TaskTrait};
+use crate::travel::{resolve, with_matrices, CostMatrices, IMPASSABLE};
+use screeps::{Position, ReturnCode, RoomName, Terrain};
+use std::collections::{HashMap, HashSet};
 
 const CONSIDER_CREEP_EXPIRED_AT: u32 = 150;
 
@@ -8,6 +16,12 @@ pub struct Game {
     pub counter: u32,
     pub creeps: HashMap<String, Creep>,
     pub spawns: HashMap<String, Spawn>,
+    pub rooms: HashMap<RoomName, Room>,
+    pub reservations: Reservations,
+    pub logistics: Logistics,
+    pub roles: Roles,
+    pub spawn_queues: HashMap<RoomName, SpawnQueue>,
+    idle_hook: Option<Box<IdleHook>>,
 }
 
 impl Game {
@@ -16,46 +30,301 @@ impl Game {
             counter: 0,
             creeps: HashMap::new(),
             spawns: HashMap::new(),
+            rooms: HashMap::new(),
+            reservations: Reservations::new(),
+            logistics: Logistics::new(),
+            roles: Roles::new(),
+            spawn_queues: HashMap::new(),
+            idle_hook: None,
+        }
+    }
+
+    /// Runs one tick of the bot, `game_loop` gets to look at the refreshed state, and queue
+    /// spawns or push tasks, before spawns and tasks act on it.
+    pub fn tick(&mut self, game_loop: &dyn Fn(&mut Game)) {
+        self.refresh_state();
+
+        game_loop(self);
+
+        self.run_spawns();
+
+        self.reserve_tasks();
+
+        self.run_logistics();
+
+        self.run_tasks();
+
+        self.run_traffic();
+
+        self.flush_memory();
+
+        with_matrices(CostMatrices::save);
+
+        if api().time() % 128 == 3 {
+            info!("running memory cleanup");
+            self.cleanup_memory();
+        }
+    }
+
+    /// Registers a role, creeps named after it pick up its jobs and get their work from it.
+    pub fn register_role<R: Role + 'static>(&mut self, role: R) {
+        let name = role.name().to_string();
+        self.roles.register(Box::new(role));
+        // creeps we already know about were unassigned until now
+        for creep in self.creeps.values_mut() {
+            if creep.job().is_unassigned() {
+                creep.set_job(self.roles.job_for(creep.name()));
+            }
+        }
+        info!("registered role {}", name);
+    }
+
+    /// Roles with fewer active creeps than they want, along with how many are missing.
+    pub fn role_deficits(&self) -> Vec<(&dyn Role, usize)> {
+        self.roles
+            .iter()
+            .filter_map(|role| {
+                let alive = self.get_active_creep_by_job(&Job::new(role.name())).len();
+                match role.population_in(&self.rooms).saturating_sub(alive) {
+                    0 => None,
+                    missing => Some((role, missing)),
+                }
+            })
+            .collect()
+    }
+
+    /// Registers the fallback that hands out work to creeps whose task queue ran empty and
+    /// whose role has nothing for them either.
+    pub fn set_idle_hook<F>(&mut self, hook: F)
+    where
+        F: Fn(&Creep) -> Option<Task> + Send + 'static,
+    {
+        self.idle_hook = Some(Box::new(hook));
+    }
+
+    /// Persists everything that has to survive a global reset, run once at the end of a tick.
+    pub fn flush_memory(&mut self) {
+        for creep in self.creeps.values_mut() {
+            creep.flush_tasks();
+            creep.flush_travel();
+        }
+    }
+
+    pub fn enqueue_spawn(&mut self, room: RoomName, request: SpawnRequest) {
+        self.spawn_queues.entry(room).or_default().push(request);
+    }
+
+    /// Queues the creeps roles are missing that aren't queued yet, in the room of the spawn
+    /// with the most energy to spend on them.
+    fn queue_role_deficits(&mut self) {
+        let spawn = self.spawns.values().max_by(|a, b| {
+            a.room_energy_capacity()
+                .cmp(&b.room_energy_capacity())
+                .then_with(|| b.name().cmp(a.name()))
+        });
+        let (room, energy) = match spawn {
+            Some(spawn) => (*spawn.room_id(), spawn.room_energy_capacity()),
+            None => return,
+        };
+        let mut requests = vec![];
+        for (role, missing) in self.role_deficits() {
+            let job = Job::new(role.name());
+            let queued = self
+                .spawn_queues
+                .values()
+                .flat_map(SpawnQueue::iter)
+                .filter(|request| *request.job() == job)
+                .count();
+            let body = role.body(energy);
+            for _ in queued..missing {
+                requests.push(SpawnRequest::new(job.clone(), body.clone()));
+            }
+        }
+        for request in requests {
+            debug!("queueing spawn of {} in {}", request.job(), room);
+            self.enqueue_spawn(room, request);
+        }
+    }
+
+    /// Queues the creeps roles are missing, then lets every idle spawn work on the most
+    /// important request of its room it can afford.
+    pub fn run_spawns(&mut self) {
+        let time = api().time();
+        for queue in self.spawn_queues.values_mut() {
+            queue.expire(time);
+        }
+        self.queue_role_deficits();
+
+        // spawns of a room share its energy, what one of them spends the others can't
+        let mut energy: HashMap<RoomName, u32> = self
+            .spawns
+            .values()
+            .map(|spawn| (*spawn.room_id(), spawn.room_energy()))
+            .collect();
+        let mut spawns: Vec<&Spawn> = self.spawns.values().collect();
+        spawns.sort_by(|a, b| a.name().cmp(b.name()));
+        for spawn in spawns {
+            if spawn.is_busy() {
+                continue;
+            }
+            let queue = match self.spawn_queues.get_mut(spawn.room_id()) {
+                Some(queue) => queue,
+                None => continue,
+            };
+            let index = match queue.next(spawn.room_energy_capacity()) {
+                Some(index) => index,
+                None => continue,
+            };
+            let request = queue.get(index).expect("index from the same queue");
+            let budget = energy.entry(*spawn.room_id()).or_default();
+            if request.cost() > *budget {
+                // keep waiting for the extensions to fill up
+                continue;
+            }
+            match spawn.spawn_request(request) {
+                ReturnCode::Ok => {
+                    info!("{} spawning {}", spawn.name(), request.job());
+                    *budget -= request.cost();
+                    queue.remove(index);
+                }
+                ReturnCode::Busy | ReturnCode::NotEnough => {}
+                r => {
+                    warn!(
+                        "{} dropping spawn request for {}: {:?}",
+                        spawn.name(),
+                        request.job(),
+                        r
+                    );
+                    queue.remove(index);
+                }
+            }
+        }
+    }
+
+    /// Clears last tick's claims and has every queued task claim its targets anew, so claims
+    /// follow what creeps are actually doing.
+    pub fn reserve_tasks(&mut self) {
+        self.reservations.clear();
+        let mut creeps: Vec<&Creep> = self.creeps.values().collect();
+        creeps.sort_by(|a, b| a.name().cmp(b.name()));
+        for creep in creeps {
+            for task in creep.tasks() {
+                task.reserve(creep, &mut self.reservations);
+            }
+        }
+    }
+
+    /// Publishes this tick's energy requests and offers and hands them out to idle haulers.
+    pub fn run_logistics(&mut self) {
+        self.logistics.clear();
+        for room in self.rooms.values() {
+            self.logistics.publish_room(room);
+        }
+
+        let job = Job::new(Hauler::NAME);
+        let mut haulers: Vec<&mut Creep> = self
+            .creeps
+            .values_mut()
+            .filter(|creep| *creep.job() == job && !creep.spawning() && creep.tasks().is_empty())
+            .collect();
+        // keeps who gets the better pairs stable from tick to tick
+        haulers.sort_by(|a, b| a.name().cmp(b.name()));
+        for hauler in haulers {
+            for task in self.logistics.assign(hauler, &mut self.reservations) {
+                hauler.push_task(task);
+            }
+        }
+    }
+
+    pub fn run_tasks(&mut self) {
+        let start_time = api().cpu_used();
+        let roles = &self.roles;
+        let idle_hook = self.idle_hook.as_deref();
+        let idle = |creep: &Creep, rooms: &HashMap<RoomName, Room>| {
+            roles
+                .next_task(creep, rooms)
+                .or_else(|| idle_hook.and_then(|hook| hook(creep)))
+        };
+        let mut context = TaskContext {
+            reservations: &mut self.reservations,
+            rooms: &mut self.rooms,
+        };
+        for creep in self.creeps.values_mut() {
+            let log_context = LogContext::creep(creep.name(), *creep.room_id());
+            with_context(log_context, || run_creep_tasks(creep, &idle, &mut context));
+        }
+        debug!("running tasks took: {}", api().cpu_used() - start_time);
+    }
+
+    /// Makes the moves creeps asked for while running their tasks, after getting those in the
+    /// way out of it, see `travel::resolve`.
+    pub fn run_traffic(&mut self) {
+        let api = api();
+        let walkable = |pos: Position| {
+            let cost = with_matrices(|matrices| {
+                matrices
+                    .structures(pos.room_name())
+                    .get(pos.x() as u8, pos.y() as u8)
+            });
+            cost < IMPASSABLE && api.terrain(pos) != Terrain::Wall
+        };
+        let creeps: Vec<&Creep> = self.creeps.values().collect();
+        for (name, direction) in resolve(&creeps, walkable) {
+            let r = api.move_direction(&name, direction);
+            if r != ReturnCode::Ok {
+                debug!("{} couldn't move {:?}: {:?}", name, direction, r);
+            }
         }
     }
 
     pub fn refresh_state(&mut self) {
-        let start_time = screeps::game::cpu::get_used();
-        debug!(
-            "game refresh starting! CPU: {}",
-            screeps::game::cpu::get_used()
-        );
+        let start_time = api().cpu_used();
+        debug!("game refresh starting! CPU: {}", start_time);
         debug!("counter: {}", self.counter);
         self.counter += 1;
 
+        self.refresh_rooms();
         self.refresh_spawns();
         self.refresh_creeps();
+        with_matrices(|matrices| matrices.refresh(&self.rooms));
 
         debug!("We have {} creeps", self.creeps.len());
 
-        let duration = screeps::game::cpu::get_used() - start_time;
+        let duration = api().cpu_used() - start_time;
         debug!(
             "game refresh ended! duration: {} initial CPU: {}",
             duration, start_time
         );
     }
 
+    fn refresh_rooms(&mut self) {
+        let visible: HashSet<RoomName> = api().rooms().into_iter().collect();
+        self.rooms.retain(|name, _| visible.contains(name));
+        for name in visible {
+            match self.rooms.get_mut(&name) {
+                Some(room) => room.refresh(),
+                None => {
+                    self.rooms.insert(name, Room::from(name));
+                }
+            }
+        }
+    }
+
     fn refresh_creeps(&mut self) {
-        for creep in screeps::game::creeps::values() {
-            // hack to get typing to work
-            let _creep: screeps::Creep = creep;
-            let _name: String = _creep.name();
-            match self.creeps.get_mut(_creep.name().as_str()) {
+        let mut creep_names: HashSet<String> = HashSet::new();
+        for creep in api().creeps() {
+            creep_names.insert(creep.name.clone());
+            match self.creeps.get_mut(&creep.name) {
                 Some(job_creep) => {
-                    job_creep.refresh(_creep);
+                    job_creep.refresh(creep);
                 }
                 None => {
-                    self.creeps.insert(_name, Creep::from(_creep));
+                    self.creeps
+                        .insert(creep.name.clone(), Creep::from(creep, &self.roles));
                 }
             }
         }
 
-        let creep_names: Vec<String> = screeps::game::creeps::keys();
         let mut expired_creeps: Vec<String> = vec![];
         for creep in self.creeps.values() {
             let _name: &String = &creep.name().to_string();
@@ -72,27 +341,28 @@ impl Game {
 
         for expired_creep in expired_creeps {
             info!("cleaning out creep {}", expired_creep);
-            // we might want to run destructors here...
+            self.reservations.release_creep(&expired_creep);
+            for room in self.rooms.values_mut() {
+                room.release_creep(&expired_creep);
+            }
             self.creeps.remove(expired_creep.as_str());
         }
     }
 
     fn refresh_spawns(&mut self) {
-        for spawn in screeps::game::spawns::values() {
-            // hack to get typing to work
-            let _spawn: screeps::StructureSpawn = spawn;
-            let _name_str = _spawn.name();
-            match self.spawns.get_mut(&_name_str) {
+        let mut spawn_names: HashSet<String> = HashSet::new();
+        for spawn in api().spawns() {
+            spawn_names.insert(spawn.name.clone());
+            match self.spawns.get_mut(&spawn.name) {
                 Some(own_spawn) => {
-                    own_spawn.refresh(_spawn);
+                    own_spawn.refresh(spawn);
                 }
                 None => {
-                    self.spawns.insert(_spawn.name(), Spawn::from(_spawn));
+                    self.spawns.insert(spawn.name.clone(), Spawn::from(spawn));
                 }
             }
         }
 
-        let spawn_names: Vec<String> = screeps::game::spawns::keys();
         let mut destroyed_spawns: Vec<String> = vec![];
         for spawn in self.spawns.values() {
             let _name: &String = &spawn.name().to_string();
@@ -110,6 +380,18 @@ impl Game {
         }
     }
 
+    /// Drops `Memory.creeps` entries of creeps that are gone.
+    pub fn cleanup_memory(&self) {
+        let alive_creeps: HashSet<String> =
+            api().creeps().into_iter().map(|creep| creep.name).collect();
+        for mem_name in api().creep_memory_names() {
+            if !alive_creeps.contains(&mem_name) {
+                debug!("cleaning up creep memory of dead creep {}", mem_name);
+                api().delete_creep_memory(&mem_name);
+            }
+        }
+    }
+
     pub fn get_active_creep_by_job(&self, job: &Job) -> Vec<&Creep> {
         self.get_creep_by_job(job, true)
     }
@@ -127,3 +409,165 @@ impl Game {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::pos;
+    use crate::api::{Intent, MockApi, Store};
+    use screeps::{Part, StructureType};
+
+    #[test]
+    fn refresh_picks_up_new_creeps_and_drops_expired_ones() {
+        let mock = MockApi::install();
+        mock.world_mut().add_creep("Harvester:1", pos(10, 10), 50);
+        mock.world_mut().add_creep("Harvester:2", pos(11, 10), 50);
+        let mut game = Game::new();
+        game.refresh_state();
+        assert_eq!(game.creeps.len(), 2);
+
+        game.reservations.set_capacity("source", 2);
+        game.reservations.claim("source", "Harvester:2", 1);
+        mock.world_mut().creeps.remove("Harvester:2");
+        mock.world_mut()
+            .creeps
+            .get_mut("Harvester:1")
+            .unwrap()
+            .energy = 20;
+        game.refresh_state();
+
+        assert_eq!(game.creeps.len(), 1);
+        assert_eq!(game.creeps["Harvester:1"].energy(), 20);
+        assert_eq!(game.reservations.remaining("source"), Some(2));
+    }
+
+    #[test]
+    fn spawns_work_on_the_most_important_affordable_request() {
+        let mock = MockApi::install();
+        mock.world_mut()
+            .add_spawn("Spawn1", pos(25, 25))
+            .room_energy = 250;
+        let mut game = Game::new();
+        game.refresh_state();
+
+        let room = *game.spawns["Spawn1"].room_id();
+        let cheap = vec![Part::Work, Part::Carry, Part::Move];
+        let expensive = vec![Part::Work, Part::Work, Part::Carry, Part::Move];
+        game.enqueue_spawn(room, SpawnRequest::new(Job::new("Hauler"), cheap.clone()));
+        game.enqueue_spawn(
+            room,
+            SpawnRequest::new(Job::new("Miner"), expensive).priority(5),
+        );
+        game.run_spawns();
+
+        // the miner doesn't fit into 250 energy yet and blocks the queue until it does
+        assert!(mock.take_intents().is_empty());
+        assert_eq!(game.spawn_queues[&room].len(), 2);
+
+        mock.world_mut()
+            .spawns
+            .get_mut("Spawn1")
+            .unwrap()
+            .room_energy = 300;
+        game.refresh_state();
+        game.run_spawns();
+        match mock.take_intents().as_slice() {
+            [Intent::Spawn { spawn, name, .. }] => {
+                assert_eq!(spawn, "Spawn1");
+                assert!(name.starts_with("Miner:"));
+            }
+            intents => panic!("unexpected intents {:?}", intents),
+        }
+        assert_eq!(game.spawn_queues[&room].len(), 1);
+    }
+
+    #[test]
+    fn spawns_of_a_room_share_its_energy() {
+        let mock = MockApi::install();
+        mock.world_mut().add_spawn("Spawn1", pos(25, 25));
+        mock.world_mut().add_spawn("Spawn2", pos(30, 25));
+        let mut game = Game::new();
+        game.refresh_state();
+
+        let room = *game.spawns["Spawn1"].room_id();
+        let body = vec![Part::Work, Part::Carry, Part::Move, Part::Move];
+        for _ in 0..2 {
+            game.enqueue_spawn(room, SpawnRequest::new(Job::new("Worker"), body.clone()));
+        }
+        game.run_spawns();
+
+        // 300 energy pay for one of them, the other one waits
+        match mock.take_intents().as_slice() {
+            [Intent::Spawn { spawn, .. }] => assert_eq!(spawn, "Spawn1"),
+            intents => panic!("unexpected intents {:?}", intents),
+        }
+        assert_eq!(game.spawn_queues[&room].len(), 1);
+    }
+
+    #[test]
+    fn claims_of_queued_tasks_outlive_the_task_before_them() {
+        let mock = MockApi::install();
+        let (spawn_id, container_id) = {
+            let mut world = mock.world_mut();
+            world.add_spawn("Spawn1", pos(25, 25));
+            let spawn = world.structures.values_mut().next().unwrap();
+            spawn.store = Some(Store {
+                energy: 200,
+                used: 200,
+                capacity: 300,
+            });
+            let spawn_id = spawn.id.clone();
+            let container = world.add_structure(pos(10, 10), StructureType::Container);
+            container.store = Some(Store {
+                energy: 500,
+                used: 500,
+                capacity: 2000,
+            });
+            container.withdrawable = true;
+            let container_id = container.id.clone();
+            world.add_creep("Hauler:1", pos(11, 10), 100);
+            world.add_creep("Hauler:2", pos(40, 40), 100);
+            (spawn_id, container_id)
+        };
+        let mut game = Game::new();
+        game.register_role(Hauler::new(2));
+        game.refresh_state();
+        game.reserve_tasks();
+        game.run_logistics();
+        assert_eq!(game.creeps["Hauler:1"].tasks().len(), 2);
+
+        // the claims are made anew the next tick, before anyone else is matched
+        game.refresh_state();
+        game.reserve_tasks();
+        assert_eq!(game.reservations.reserved(&container_id), 100);
+        assert_eq!(game.reservations.reserved(&spawn_id), 100);
+        game.run_logistics();
+        assert!(game.creeps["Hauler:2"].tasks().is_empty());
+
+        // once it's full, the withdraw is done and only its claim goes
+        {
+            let mut world = mock.world_mut();
+            let hauler = world.creeps.get_mut("Hauler:1").unwrap();
+            hauler.energy = 100;
+            hauler.carry_total = 100;
+        }
+        game.refresh_state();
+        game.reserve_tasks();
+        game.run_logistics();
+        game.run_tasks();
+        assert_eq!(game.creeps["Hauler:1"].tasks().len(), 1);
+        assert_eq!(game.reservations.reserved(&container_id), 0);
+        assert_eq!(game.reservations.reserved(&spawn_id), 100);
+        assert!(game.creeps["Hauler:2"].tasks().is_empty());
+    }
+
+    #[test]
+    fn memory_of_dead_creeps_is_cleaned_up() {
+        let mock = MockApi::install();
+        mock.world_mut().add_creep("Harvester:1", pos(10, 10), 50);
+        api().set_creep_memory("Harvester:1", "tasks", Some("[]"));
+        api().set_creep_memory("Harvester:0", "tasks", Some("[]"));
+        Game::new().cleanup_memory();
+        assert_eq!(api().creep_memory_names(), vec!["Harvester:1".to_string()]);
+    }
+}
@@ -0,0 +1,344 @@
+use crate::api::Owner;
+use crate::data::{Creep, Reservations, Room};
+use crate::tasks::{Pickup, PileKind, Task, Transfer, Withdraw};
+use screeps::{Position, StructureType};
+use std::cmp::Reverse;
+
+/// Spawns and extensions come first, nothing else gets spawned without them.
+pub const SPAWN_PRIORITY: u32 = 3;
+pub const TOWER_PRIORITY: u32 = 2;
+pub const UPGRADE_PRIORITY: u32 = 1;
+
+/// Range from the controller within which a container feeds upgraders instead of being a
+/// store to take energy from.
+const UPGRADE_CONTAINER_RANGE: u32 = 3;
+
+/// Energy a structure wants delivered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub target: String,
+    pub pos: Position,
+    pub amount: u32,
+    pub priority: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OfferKind {
+    Structure,
+    Pile(PileKind),
+}
+
+/// Energy that can be taken from a structure or a pile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Offer {
+    pub source: String,
+    pub kind: OfferKind,
+    pub pos: Position,
+    pub amount: u32,
+}
+
+/// Structures energy can be taken from, when they're not ours they have to be neutral.
+const OFFER_TYPES: [StructureType; 3] = [
+    StructureType::Container,
+    StructureType::Storage,
+    StructureType::Link,
+];
+
+/// Matches haulers to pairs of energy offers and requests, published anew every tick.
+#[derive(Default)]
+pub struct Logistics {
+    requests: Vec<Request>,
+    offers: Vec<Offer>,
+    // where haulers carrying energy nobody asked for drop it off, storage before containers
+    stores: Vec<Request>,
+}
+
+impl Logistics {
+    pub fn new() -> Logistics {
+        Logistics {
+            requests: vec![],
+            offers: vec![],
+            stores: vec![],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.requests.clear();
+        self.offers.clear();
+        self.stores.clear();
+    }
+
+    pub fn request(&mut self, request: Request) {
+        self.requests.push(request);
+    }
+
+    pub fn offer(&mut self, offer: Offer) {
+        self.offers.push(offer);
+    }
+
+    pub fn requests(&self) -> &[Request] {
+        &self.requests
+    }
+
+    pub fn offers(&self) -> &[Offer] {
+        &self.offers
+    }
+
+    /// Publishes what the structures of `room` need and what they and its piles have.
+    pub fn publish_room(&mut self, room: &Room) {
+        let upgrade_container = |pos: &Position| {
+            room.is_mine()
+                && room
+                    .controller()
+                    .is_some_and(|c| c.pos.get_range_to(pos) <= UPGRADE_CONTAINER_RANGE)
+        };
+        for structure in room.structures() {
+            let priority = match structure.structure_type {
+                StructureType::Spawn | StructureType::Extension => Some(SPAWN_PRIORITY),
+                StructureType::Tower => Some(TOWER_PRIORITY),
+                StructureType::Container if upgrade_container(&structure.pos) => {
+                    Some(UPGRADE_PRIORITY)
+                }
+                _ => None,
+            };
+            let free = structure.free_energy_capacity().unwrap_or(0);
+            let stored = structure.stored_energy().unwrap_or(0);
+            let hostile = structure.owner == Owner::Hostile;
+            let store = match structure.structure_type {
+                StructureType::Storage => Some(1),
+                StructureType::Container if priority.is_some() => Some(0),
+                _ => None,
+            };
+            if let Some(store) = store.filter(|_| !hostile && free > 0) {
+                self.stores.push(Request {
+                    target: structure.id.clone(),
+                    pos: structure.pos,
+                    amount: free,
+                    priority: store,
+                });
+            }
+            match priority {
+                Some(priority) if !hostile && free > 0 => self.request(Request {
+                    target: structure.id.clone(),
+                    pos: structure.pos,
+                    amount: free,
+                    priority,
+                }),
+                None if structure.withdrawable
+                    && !hostile
+                    && OFFER_TYPES.contains(&structure.structure_type)
+                    && stored > 0 =>
+                {
+                    self.offer(Offer {
+                        source: structure.id.clone(),
+                        kind: OfferKind::Structure,
+                        pos: structure.pos,
+                        amount: stored,
+                    })
+                }
+                _ => {}
+            }
+        }
+        for pile in room.piles() {
+            self.offer(Offer {
+                source: pile.id.clone(),
+                kind: OfferKind::Pile(pile.kind),
+                pos: pile.pos,
+                amount: pile.amount,
+            });
+        }
+    }
+
+    /// Work for an idle `hauler`, claiming what it plans to move. Empty if there's nothing to
+    /// do.
+    pub fn assign(&self, hauler: &Creep, reservations: &mut Reservations) -> Vec<Task> {
+        let pos = *hauler.pos();
+        let remaining = |id: &str, amount: u32, reservations: &Reservations| {
+            amount.saturating_sub(reservations.reserved(id))
+        };
+        let requests: Vec<(&Request, u32)> = self
+            .requests
+            .iter()
+            .filter(|request| request.pos.room_name() == pos.room_name())
+            .map(|request| {
+                let left = remaining(&request.target, request.amount, reservations);
+                (request, left)
+            })
+            .filter(|(_, left)| *left > 0)
+            .collect();
+
+        if hauler.energy() > 0 {
+            let request = requests.iter().min_by_key(|(request, _)| {
+                (Reverse(request.priority), pos.get_range_to(&request.pos))
+            });
+            return match request {
+                Some((request, left)) => {
+                    reservations.claim(&request.target, hauler.name(), hauler.energy().min(*left));
+                    vec![Transfer::new(request.target.clone()).into()]
+                }
+                None => self
+                    .stores
+                    .iter()
+                    .filter(|store| store.pos.room_name() == pos.room_name())
+                    .min_by_key(|store| (Reverse(store.priority), pos.get_range_to(&store.pos)))
+                    .map(|store| Transfer::new(store.target.clone()).into())
+                    .into_iter()
+                    .collect(),
+            };
+        }
+
+        let free = hauler.carry_capacity() - hauler.carry_total();
+        let offers: Vec<(&Offer, u32)> = self
+            .offers
+            .iter()
+            .filter(|offer| offer.pos.room_name() == pos.room_name())
+            .map(|offer| (offer, remaining(&offer.source, offer.amount, reservations)))
+            .filter(|(_, left)| *left > 0)
+            .collect();
+        let best = requests
+            .iter()
+            .flat_map(|request| offers.iter().map(move |offer| (request, offer)))
+            .map(|((request, request_left), (offer, offer_left))| {
+                let amount = free.min(*request_left).min(*offer_left);
+                let distance = pos.get_range_to(&offer.pos) + offer.pos.get_range_to(&request.pos);
+                (request, offer, amount, amount * 100 / (distance + 1))
+            })
+            .min_by_key(|(request, _, _, yield_)| (Reverse(request.priority), Reverse(*yield_)));
+        match best {
+            Some((request, offer, amount, _)) if amount > 0 => {
+                reservations.claim(&offer.source, hauler.name(), amount);
+                reservations.claim(&request.target, hauler.name(), amount);
+                let pick_up = match offer.kind {
+                    OfferKind::Structure => Withdraw::new(offer.source.clone()).into(),
+                    OfferKind::Pile(kind) => Pickup::new(offer.source.clone(), kind).into(),
+                };
+                vec![
+                    pick_up,
+                    Transfer::new(request.target.clone()).amount(amount).into(),
+                ]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{self, pos, room_name};
+    use crate::api::{MockApi, Store};
+
+    #[test]
+    fn haulers_bring_the_closest_energy_to_the_most_important_request() {
+        let mock = MockApi::install();
+        let (spawn_id, container_id, pile_id) = {
+            let mut world = mock.world_mut();
+            world.add_controller(pos(40, 40));
+            world.add_spawn("Spawn1", pos(25, 25));
+            let spawn = world.structures.values_mut().next().unwrap();
+            spawn.store = Some(Store {
+                energy: 100,
+                used: 100,
+                capacity: 300,
+            });
+            let spawn_id = spawn.id.clone();
+            // next to the controller, so it wants energy instead of handing it out
+            let upgrade = world.add_structure(pos(38, 38), StructureType::Container);
+            upgrade.store = Some(Store {
+                energy: 0,
+                used: 0,
+                capacity: 2000,
+            });
+            upgrade.withdrawable = true;
+            upgrade.transferable = true;
+            let container = world.add_structure(pos(10, 10), StructureType::Container);
+            container.store = Some(Store {
+                energy: 500,
+                used: 500,
+                capacity: 2000,
+            });
+            container.withdrawable = true;
+            let container_id = container.id.clone();
+            let pile_id = world
+                .add_pile(pos(24, 20), PileKind::Dropped, 100)
+                .id
+                .clone();
+            world.add_creep("Hauler:1", pos(25, 20), 150);
+            world.add_creep("Hauler:2", pos(25, 20), 150);
+            (spawn_id, container_id, pile_id)
+        };
+        let mut logistics = Logistics::new();
+        logistics.publish_room(&Room::from(room_name()));
+        assert_eq!(logistics.requests().len(), 2);
+        assert_eq!(logistics.offers().len(), 2);
+
+        let first = fixtures::creep("Hauler:1");
+        let second = fixtures::creep("Hauler:2");
+        let mut reservations = Reservations::new();
+
+        // the pile right next to the hauler covers most of what the spawn is missing
+        assert_eq!(
+            logistics.assign(&first, &mut reservations),
+            vec![
+                Pickup::new(pile_id, PileKind::Dropped).into(),
+                Transfer::new(spawn_id.clone()).amount(100).into(),
+            ]
+        );
+        // the rest of the spawn is worth a longer trip to the container
+        assert_eq!(
+            logistics.assign(&second, &mut reservations),
+            vec![
+                Withdraw::new(container_id).into(),
+                Transfer::new(spawn_id.clone()).amount(100).into(),
+            ]
+        );
+        assert_eq!(reservations.reserved(&spawn_id), 200);
+    }
+
+    #[test]
+    fn hostile_and_special_structures_are_no_offers_and_leftovers_go_into_storage() {
+        let mock = MockApi::install();
+        let storage_id = {
+            let mut world = mock.world_mut();
+            world.add_controller(pos(40, 40));
+            world.add_spawn("Spawn1", pos(25, 25));
+            let hostile = world.add_structure(pos(10, 10), StructureType::Container);
+            hostile.owner = Owner::Hostile;
+            hostile.store = Some(Store {
+                energy: 500,
+                used: 500,
+                capacity: 2000,
+            });
+            hostile.withdrawable = true;
+            let terminal = world.add_structure(pos(12, 10), StructureType::Terminal);
+            terminal.store = Some(Store {
+                energy: 500,
+                used: 500,
+                capacity: 300_000,
+            });
+            terminal.withdrawable = true;
+            let storage = world.add_structure(pos(30, 30), StructureType::Storage);
+            storage.store = Some(Store {
+                energy: 0,
+                used: 0,
+                capacity: 1_000_000,
+            });
+            storage.transferable = true;
+            let storage_id = storage.id.clone();
+            let hauler = world.add_creep("Hauler:1", pos(25, 20), 100);
+            hauler.energy = 100;
+            hauler.carry_total = 100;
+            storage_id
+        };
+        let mut logistics = Logistics::new();
+        logistics.publish_room(&Room::from(room_name()));
+        assert!(logistics.requests().is_empty());
+        assert!(logistics.offers().is_empty());
+
+        let hauler = fixtures::creep("Hauler:1");
+        assert_eq!(
+            logistics.assign(&hauler, &mut Reservations::new()),
+            vec![Transfer::new(storage_id).into()]
+        );
+    }
+}
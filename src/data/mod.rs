@@ -1,8 +1,22 @@
+mod body;
 mod creep;
 mod game;
+mod logistics;
+mod reservations;
+mod role;
+mod room;
+mod source;
 mod spawn;
+mod spawn_queue;
 
+pub use self::body::{body_cost, BodyBuilder, MAX_BODY_PARTS};
 pub use self::creep::Creep;
 pub use self::creep::Job;
 pub use self::game::Game;
-pub use self::spawn::Spawn;
+pub use self::logistics::{Logistics, Offer, OfferKind, Request};
+pub use self::reservations::Reservations;
+pub use self::role::{Role, Roles};
+pub use self::room::Room;
+pub use self::source::Source;
+pub use self::spawn::{Spawn, SpawningCreep};
+pub use self::spawn_queue::{SpawnQueue, SpawnRequest};
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+/// Which creep claimed how much of a target this tick, so tasks picking targets spread out.
+/// Claimed anew every tick by the tasks still queued, see `TaskTrait::reserve`.
+#[derive(Default)]
+pub struct Reservations {
+    capacities: HashMap<String, u32>,
+    claims: HashMap<String, HashMap<String, u32>>,
+}
+
+impl Reservations {
+    pub fn new() -> Reservations {
+        Reservations {
+            capacities: HashMap::new(),
+            claims: HashMap::new(),
+        }
+    }
+
+    /// Drops all claims and capacities, for the next tick to set them anew.
+    pub fn clear(&mut self) {
+        self.capacities.clear();
+        self.claims.clear();
+    }
+
+    pub fn set_capacity(&mut self, target: &str, capacity: u32) {
+        self.capacities.insert(target.to_string(), capacity);
+    }
+
+    pub fn capacity(&self, target: &str) -> Option<u32> {
+        self.capacities.get(target).cloned()
+    }
+
+    /// Sum of all claims on `target`.
+    pub fn reserved(&self, target: &str) -> u32 {
+        self.claims
+            .get(target)
+            .map_or(0, |claims| claims.values().sum())
+    }
+
+    /// What is left of `target` after all claims, `None` if its capacity is unknown.
+    pub fn remaining(&self, target: &str) -> Option<u32> {
+        self.capacity(target)
+            .map(|capacity| capacity.saturating_sub(self.reserved(target)))
+    }
+
+    /// Claims `amount` of `target` for `creep`, false if not enough capacity is left.
+    pub fn claim(&mut self, target: &str, creep: &str, amount: u32) -> bool {
+        if let Some(capacity) = self.capacity(target) {
+            let others = self.reserved(target) - self.claimed_by(target, creep);
+            if others + amount > capacity {
+                return false;
+            }
+        }
+        self.claims
+            .entry(target.to_string())
+            .or_default()
+            .insert(creep.to_string(), amount);
+        true
+    }
+
+    /// Claims as much of `amount` as is left of `target`, returning what was claimed.
+    pub fn claim_available(&mut self, target: &str, creep: &str, amount: u32) -> u32 {
+        self.release(target, creep);
+        let amount = self
+            .remaining(target)
+            .map_or(amount, |remaining| remaining.min(amount));
+        self.claim(target, creep, amount);
+        amount
+    }
+
+    pub fn claimed_by(&self, target: &str, creep: &str) -> u32 {
+        self.claims
+            .get(target)
+            .and_then(|claims| claims.get(creep))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    pub fn release(&mut self, target: &str, creep: &str) {
+        if let Some(claims) = self.claims.get_mut(target) {
+            claims.remove(creep);
+            if claims.is_empty() {
+                self.claims.remove(target);
+            }
+        }
+    }
+
+    /// Targets anyone claimed anything of.
+    pub fn targets(&self) -> impl Iterator<Item = &str> {
+        self.claims.keys().map(String::as_str)
+    }
+
+    /// Drops every claim `creep` holds, used when it dies.
+    pub fn release_creep(&mut self, creep: &str) {
+        for claims in self.claims.values_mut() {
+            claims.remove(creep);
+        }
+        self.claims.retain(|_, claims| !claims.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claims_are_limited_by_capacity() {
+        let mut reservations = Reservations::new();
+        reservations.set_capacity("source", 2);
+        assert!(reservations.claim("source", "a", 1));
+        assert!(reservations.claim("source", "b", 1));
+        assert!(!reservations.claim("source", "c", 1));
+        assert_eq!(reservations.remaining("source"), Some(0));
+
+        // re-claiming replaces the old claim instead of stacking on top of it
+        assert!(reservations.claim("source", "a", 1));
+        assert_eq!(reservations.reserved("source"), 2);
+    }
+
+    #[test]
+    fn released_claims_free_up_capacity() {
+        let mut reservations = Reservations::new();
+        reservations.set_capacity("container", 500);
+        assert!(reservations.claim("container", "a", 300));
+        assert!(reservations.claim("site", "a", 50));
+        assert!(!reservations.claim("container", "b", 300));
+
+        reservations.release_creep("a");
+        assert_eq!(reservations.reserved("site"), 0);
+        assert!(reservations.claim("container", "b", 300));
+        assert_eq!(reservations.remaining("container"), Some(200));
+        assert_eq!(reservations.claim_available("container", "c", 300), 200);
+        assert_eq!(reservations.remaining("unknown"), None);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::data::{Creep, Job, Room};
+use crate::tasks::Task;
+use screeps::{Part, RoomName};
+use std::collections::HashMap;
+
+/// Behaviour shared by all creeps of one kind, a creep called `Miner:1234` belongs to `Miner`.
+pub trait Role: Send {
+    fn name(&self) -> &str;
+
+    /// Body of a new creep of this role, given the energy its room can spend on it.
+    fn body(&self, energy: u32) -> Vec<Part>;
+
+    /// How many creeps of this role we want to have alive.
+    fn population(&self) -> usize;
+
+    /// How many creeps of this role we want given the rooms we can see, for roles that scale
+    /// with them. Defaults to `population`.
+    fn population_in(&self, _rooms: &HashMap<RoomName, Room>) -> usize {
+        self.population()
+    }
+
+    /// Work for a creep of this role whose task queue ran empty, given the rooms we can see.
+    fn next_task(&self, creep: &Creep, rooms: &HashMap<RoomName, Room>) -> Option<Task>;
+}
+
+#[derive(Default)]
+pub struct Roles {
+    roles: HashMap<String, Box<dyn Role>>,
+}
+
+impl Roles {
+    pub fn new() -> Roles {
+        Roles {
+            roles: HashMap::new(),
+        }
+    }
+
+    /// Adds `role`, replacing a previously registered role of the same name.
+    pub fn register(&mut self, role: Box<dyn Role>) {
+        self.roles.insert(role.name().to_string(), role);
+    }
+
+    pub fn get(&self, job: &Job) -> Option<&dyn Role> {
+        self.roles.get(job.as_str()).map(|role| role.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Role> {
+        self.roles.values().map(|role| role.as_ref())
+    }
+
+    /// Looks up the job of a creep by the role prefix of its name.
+    pub fn job_for(&self, creep_name: &str) -> Job {
+        let prefix = creep_name.split(':').next().unwrap_or_default();
+        if self.roles.contains_key(prefix) {
+            Job::new(prefix)
+        } else {
+            Job::unassigned()
+        }
+    }
+
+    pub fn next_task(&self, creep: &Creep, rooms: &HashMap<RoomName, Room>) -> Option<Task> {
+        self.get(creep.job())?.next_task(creep, rooms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Miner;
+
+    impl Role for Miner {
+        fn name(&self) -> &str {
+            "Miner"
+        }
+
+        fn body(&self, _energy: u32) -> Vec<Part> {
+            vec![Part::Work, Part::Work, Part::Move]
+        }
+
+        fn population(&self) -> usize {
+            2
+        }
+
+        fn next_task(&self, _creep: &Creep, _rooms: &HashMap<RoomName, Room>) -> Option<Task> {
+            None
+        }
+    }
+
+    #[test]
+    fn creeps_are_matched_to_roles_by_name() {
+        let mut roles = Roles::new();
+        roles.register(Box::new(Miner));
+
+        assert_eq!(roles.job_for("Miner:1234"), Job::new("Miner"));
+        assert_eq!(roles.job_for("Hauler:1234"), Job::unassigned());
+        assert_eq!(roles.job_for("Bob"), Job::unassigned());
+        assert_eq!(roles.get(&Job::new("Miner")).unwrap().population(), 2);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::api::{
+    api, ControllerState, HostileCreepState, MineralState, Owner, PileState, SiteState,
+    StructureState,
+};
+use crate::data::Source;
+use core::borrow::Borrow;
+use screeps::{RoomName, StructureType};
+use std::collections::HashMap;
+
+/// A room we can see into, with everything tasks look for cached once per tick.
+pub struct Room {
+    name: RoomName,
+    controller: Option<ControllerState>,
+    sources: Vec<Source>,
+    minerals: Vec<MineralState>,
+    structures: Vec<StructureState>,
+    // indices into `structures`, which keeps the order the API handed them out in
+    structures_by_type: HashMap<StructureType, Vec<usize>>,
+    construction_sites: Vec<SiteState>,
+    piles: Vec<PileState>,
+    hostiles: Vec<HostileCreepState>,
+}
+
+impl Room {
+    pub fn from(name: RoomName) -> Room {
+        let mut room = Room {
+            name,
+            controller: None,
+            sources: vec![],
+            minerals: vec![],
+            structures: vec![],
+            structures_by_type: HashMap::new(),
+            construction_sites: vec![],
+            piles: vec![],
+            hostiles: vec![],
+        };
+        room.refresh();
+        room
+    }
+
+    pub fn refresh(&mut self) {
+        let api = api();
+        self.controller = api.room_controller(self.name);
+        self.refresh_sources();
+        self.minerals = api.find_minerals(self.name);
+        self.structures = api.find_structures(self.name);
+        self.structures_by_type.clear();
+        for (index, structure) in self.structures.iter().enumerate() {
+            self.structures_by_type
+                .entry(structure.structure_type)
+                .or_default()
+                .push(index);
+        }
+        self.construction_sites = api.find_construction_sites(self.name);
+        self.piles = api.find_piles(self.name);
+        self.hostiles = api.find_hostile_creeps(self.name);
+    }
+
+    /// Updates the sources we know about in place, so their slots and assignments carry over.
+    fn refresh_sources(&mut self) {
+        let states = api().find_sources(self.name);
+        self.sources
+            .retain(|source| states.iter().any(|state| state.id == source.id()));
+        for state in states {
+            match self
+                .sources
+                .iter_mut()
+                .find(|source| source.id() == state.id)
+            {
+                Some(source) => source.refresh(state),
+                None => self.sources.push(Source::from(state)),
+            }
+        }
+    }
+
+    pub fn name(&self) -> &RoomName {
+        self.name.borrow()
+    }
+
+    pub fn controller(&self) -> Option<&ControllerState> {
+        self.controller.as_ref()
+    }
+
+    /// Whether we own the controller of this room.
+    pub fn is_mine(&self) -> bool {
+        self.controller.as_ref().is_some_and(|c| c.my)
+    }
+
+    pub fn sources(&self) -> &[Source] {
+        self.sources.borrow()
+    }
+
+    pub fn sources_mut(&mut self) -> &mut [Source] {
+        &mut self.sources
+    }
+
+    pub fn source_mut(&mut self, id: &str) -> Option<&mut Source> {
+        self.sources.iter_mut().find(|source| source.id() == id)
+    }
+
+    /// Frees up whatever `creep` had assigned to it in this room.
+    pub fn release_creep(&mut self, creep: &str) {
+        for source in self.sources.iter_mut() {
+            source.release(creep);
+        }
+    }
+
+    pub fn minerals(&self) -> &[MineralState] {
+        self.minerals.borrow()
+    }
+
+    /// All structures in the room, whoever owns them.
+    pub fn structures(&self) -> &[StructureState] {
+        self.structures.borrow()
+    }
+
+    pub fn structures_of(
+        &self,
+        structure_type: StructureType,
+    ) -> impl Iterator<Item = &StructureState> {
+        self.structures_by_type
+            .get(&structure_type)
+            .into_iter()
+            .flatten()
+            .map(move |index| &self.structures[*index])
+    }
+
+    /// Our structures of any of `structure_types`.
+    pub fn my_structures_of<'a>(
+        &'a self,
+        structure_types: &'a [StructureType],
+    ) -> impl Iterator<Item = &'a StructureState> {
+        self.structures.iter().filter(move |structure| {
+            structure.owner == Owner::Mine && structure_types.contains(&structure.structure_type)
+        })
+    }
+
+    pub fn hostile_structures(&self) -> impl Iterator<Item = &StructureState> {
+        self.structures
+            .iter()
+            .filter(|structure| structure.owner == Owner::Hostile)
+    }
+
+    pub fn construction_sites(&self) -> &[SiteState] {
+        self.construction_sites.borrow()
+    }
+
+    /// Dropped energy, tombstones and ruins with energy left in them.
+    pub fn piles(&self) -> &[PileState] {
+        self.piles.borrow()
+    }
+
+    pub fn hostiles(&self) -> &[HostileCreepState] {
+        self.hostiles.borrow()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{pos, room_name};
+    use crate::api::MockApi;
+    use screeps::ResourceType;
+
+    #[test]
+    fn structures_are_grouped_by_type_and_owner() {
+        let mock = MockApi::install();
+        {
+            let mut world = mock.world_mut();
+            world.add_spawn("Spawn1", pos(25, 25));
+            world.add_structure(pos(26, 26), StructureType::Extension);
+            world
+                .add_structure(pos(27, 26), StructureType::Extension)
+                .owner = Owner::Hostile;
+            world.add_mineral(pos(5, 5), ResourceType::Hydrogen);
+            world.add_hostile("Invader", "Invader", pos(10, 10));
+            world.add_controller(pos(25, 40));
+        }
+        let room = Room::from(room_name());
+
+        assert!(room.is_mine());
+        assert_eq!(room.structures().len(), 3);
+        assert_eq!(room.structures_of(StructureType::Extension).count(), 2);
+        let mine: Vec<_> = room
+            .my_structures_of(&[StructureType::Spawn, StructureType::Extension])
+            .collect();
+        assert_eq!(mine.len(), 2);
+        assert_eq!(room.hostile_structures().count(), 1);
+        assert_eq!(room.minerals().len(), 1);
+        assert_eq!(room.hostiles().len(), 1);
+        assert_eq!(room.structures_of(StructureType::Tower).count(), 0);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::api::{api, SourceState};
+use screeps::{Position, Terrain, ENERGY_REGEN_TIME, HARVEST_POWER};
+
+/// A source along with the tiles next to it creeps can harvest from, and who was given which.
+pub struct Source {
+    state: SourceState,
+    slots: Vec<Position>,
+    // creep standing on the slot at the same index
+    assigned: Vec<Option<String>>,
+    miner: Option<String>,
+}
+
+/// Tiles next to `source` that aren't walls, in a fixed order.
+fn access_slots(source: &SourceState) -> Vec<Position> {
+    let api = api();
+    let room = source.pos.room_name();
+    let (x, y) = source.pos.coords_signed();
+    let mut slots = vec![];
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let (x, y) = (x + dx, y + dy);
+            if (dx, dy) == (0, 0) || x < 0 || y < 0 || x > 49 || y > 49 {
+                continue;
+            }
+            let pos = Position::new(x as u32, y as u32, room);
+            if api.terrain(pos) != Terrain::Wall {
+                slots.push(pos);
+            }
+        }
+    }
+    slots
+}
+
+impl Source {
+    pub fn from(state: SourceState) -> Source {
+        let slots = access_slots(&state);
+        Source {
+            assigned: vec![None; slots.len()],
+            slots,
+            state,
+            miner: None,
+        }
+    }
+
+    pub fn refresh(&mut self, state: SourceState) {
+        self.state = state;
+    }
+
+    pub fn id(&self) -> &str {
+        &self.state.id
+    }
+
+    pub fn pos(&self) -> Position {
+        self.state.pos
+    }
+
+    pub fn state(&self) -> &SourceState {
+        &self.state
+    }
+
+    pub fn energy(&self) -> u32 {
+        self.state.energy
+    }
+
+    pub fn energy_capacity(&self) -> u32 {
+        self.state.energy_capacity
+    }
+
+    pub fn ticks_to_regeneration(&self) -> u32 {
+        self.state.ticks_to_regeneration
+    }
+
+    /// Whether there is energy to harvest now or after at most `ticks` of waiting.
+    pub fn has_energy_within(&self, ticks: u32) -> bool {
+        self.state.energy > 0 || self.state.ticks_to_regeneration <= ticks
+    }
+
+    /// WORK parts that drain the source just as it regenerates, any more than that idle.
+    pub fn work_parts_supported(&self) -> u32 {
+        let per_part = HARVEST_POWER * ENERGY_REGEN_TIME;
+        self.state.energy_capacity.div_ceil(per_part)
+    }
+
+    /// Tiles next to the source that creeps can harvest from.
+    pub fn slots(&self) -> &[Position] {
+        &self.slots
+    }
+
+    pub fn free_slots(&self) -> usize {
+        self.assigned.iter().filter(|creep| creep.is_none()).count()
+    }
+
+    pub fn assigned_creeps(&self) -> impl Iterator<Item = &str> {
+        self.assigned.iter().filter_map(|creep| creep.as_deref())
+    }
+
+    pub fn slot_of(&self, creep: &str) -> Option<Position> {
+        self.assigned
+            .iter()
+            .position(|assigned| assigned.as_deref() == Some(creep))
+            .map(|index| self.slots[index])
+    }
+
+    /// Gives `creep` standing at `pos` its slot, the one it stands on or the closest free one.
+    pub fn assign(&mut self, creep: &str, pos: Position) -> Option<Position> {
+        if let Some(slot) = self.slot_of(creep) {
+            return Some(slot);
+        }
+        let index = (0..self.slots.len())
+            .filter(|index| self.assigned[*index].is_none())
+            .min_by_key(|index| {
+                let slot = self.slots[*index];
+                if slot == pos {
+                    0
+                } else {
+                    1 + pos.get_range_to(&slot)
+                }
+            })?;
+        self.assigned[index] = Some(creep.to_string());
+        Some(self.slots[index])
+    }
+
+    /// The creep parked here to harvest for the rest of its life, see `tasks::Mine`.
+    pub fn miner(&self) -> Option<&str> {
+        self.miner.as_deref()
+    }
+
+    pub fn set_miner(&mut self, creep: &str) {
+        self.miner = Some(creep.to_string());
+    }
+
+    pub fn release(&mut self, creep: &str) {
+        if self.miner.as_deref() == Some(creep) {
+            self.miner = None;
+        }
+        for assigned in self.assigned.iter_mut() {
+            if assigned.as_deref() == Some(creep) {
+                *assigned = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::pos;
+    use crate::api::MockApi;
+
+    #[test]
+    fn slots_skip_walls_and_are_handed_out_once() {
+        let mock = MockApi::install();
+        let state = {
+            let mut world = mock.world_mut();
+            for x in 9..=11 {
+                world.terrain.insert(pos(x, 9), Terrain::Wall);
+            }
+            world.add_source(pos(10, 10)).clone()
+        };
+        let mut source = Source::from(state);
+        assert_eq!(source.slots().len(), 5);
+        assert_eq!(source.work_parts_supported(), 5);
+
+        // standing on a slot keeps it, everyone else gets the closest free one
+        assert_eq!(source.assign("a", pos(9, 11)), Some(pos(9, 11)));
+        assert_eq!(source.assign("b", pos(14, 10)), Some(pos(11, 10)));
+        assert_eq!(source.assign("b", pos(11, 10)), Some(pos(11, 10)));
+        assert_eq!(source.slot_of("a"), Some(pos(9, 11)));
+        assert_eq!(source.free_slots(), 3);
+
+        for creep in &["c", "d", "e"] {
+            assert!(source.assign(creep, pos(20, 20)).is_some());
+        }
+        assert_eq!(source.assign("f", pos(20, 20)), None);
+        source.release("b");
+        assert_eq!(source.assign("f", pos(20, 20)), Some(pos(11, 10)));
+    }
+}
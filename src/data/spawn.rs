@@ -1,69 +1,116 @@
-use crate::data::Job;
+use crate::api::{api, SpawnState};
+use crate::data::{Job, Role, SpawnRequest};
 use core::borrow::Borrow;
-use screeps::{
-    CanStoreEnergy, HasPosition, Part, ReturnCode, Room, RoomName, RoomObjectProperties,
-    RoomPosition,
-};
+use screeps::{Part, Position, ReturnCode, RoomName};
+
+pub use crate::api::SpawningCreep;
 
 pub struct Spawn {
-    _source: screeps::StructureSpawn,
     name: String,
-    pos: RoomPosition,
-    room: Room,
+    pos: Position,
     room_id: RoomName,
+    spawning: Option<SpawningCreep>,
+    energy: u32,
+    energy_capacity: u32,
+    room_energy: u32,
+    room_energy_capacity: u32,
+    hits: u32,
+    hits_max: u32,
 }
 
 impl Spawn {
-    pub fn from(spawn: screeps::StructureSpawn) -> Spawn {
-        let _source = spawn.borrow();
-        let name: String = _source.name();
-        let pos = _source.pos();
-        let room = _source.room();
-        let room_id = room.name();
+    pub fn from(spawn: SpawnState) -> Spawn {
         Spawn {
-            _source: spawn,
-            name,
-            pos,
-            room,
-            room_id,
+            name: spawn.name,
+            pos: spawn.pos,
+            room_id: spawn.pos.room_name(),
+            spawning: spawn.spawning,
+            energy: spawn.energy,
+            energy_capacity: spawn.energy_capacity,
+            room_energy: spawn.room_energy,
+            room_energy_capacity: spawn.room_energy_capacity,
+            hits: spawn.hits,
+            hits_max: spawn.hits_max,
         }
     }
 
-    pub fn refresh(&mut self, spawn: screeps::StructureSpawn) {
-        let _source = spawn.borrow();
-        self._source = spawn;
+    pub fn refresh(&mut self, spawn: SpawnState) {
+        self.pos = spawn.pos;
+        self.room_id = spawn.pos.room_name();
+        self.spawning = spawn.spawning;
+        self.energy = spawn.energy;
+        self.energy_capacity = spawn.energy_capacity;
+        self.room_energy = spawn.room_energy;
+        self.room_energy_capacity = spawn.room_energy_capacity;
+        self.hits = spawn.hits;
+        self.hits_max = spawn.hits_max;
     }
 
     pub fn name(&self) -> &str {
         self.name.borrow()
     }
 
-    pub fn pos(&self) -> &RoomPosition {
+    pub fn pos(&self) -> &Position {
         self.pos.borrow()
     }
 
-    pub fn room(&self) -> &Room {
-        self.room.borrow()
-    }
-
     pub fn room_id(&self) -> &RoomName {
         self.room_id.borrow()
     }
 
     pub fn energy(&self) -> u32 {
-        self._source.energy()
+        self.energy
     }
 
     pub fn energy_capacity(&self) -> u32 {
-        self._source.energy_capacity()
+        self.energy_capacity
+    }
+
+    /// Energy available for spawning in the whole room, spawns and extensions.
+    pub fn room_energy(&self) -> u32 {
+        self.room_energy
+    }
+
+    pub fn room_energy_capacity(&self) -> u32 {
+        self.room_energy_capacity
+    }
+
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    pub fn hits_max(&self) -> u32 {
+        self.hits_max
+    }
+
+    pub fn spawning(&self) -> Option<&SpawningCreep> {
+        self.spawning.as_ref()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.spawning.is_some()
     }
 
     pub fn spawn_job_creep(&self, body: &[Part], job: Job) -> ReturnCode {
-        let name = screeps::game::time();
+        self.spawn_named(&job, |name| self.spawn_creep(body, name))
+    }
+
+    pub fn spawn_request(&self, request: &SpawnRequest) -> ReturnCode {
+        self.spawn_named(request.job(), |name| {
+            api().spawn_creep(&self.name, request.body(), name, request.memory_entries())
+        })
+    }
+
+    /// Spawns using a `<job>:<tick><n>` name, counting `n` up until the name is free.
+    fn spawn_named<F>(&self, job: &Job, spawn: F) -> ReturnCode
+    where
+        F: Fn(&str) -> ReturnCode,
+    {
+        let name = api().time();
         let mut additional = 0;
         loop {
             let name = format!("{}:{}{}", job.as_str(), name, additional);
-            let res = self.spawn_creep(&body, &name);
+            let res = spawn(&name);
 
             if res == ReturnCode::NameExists {
                 additional += 1;
@@ -73,7 +120,11 @@ impl Spawn {
         }
     }
 
+    pub fn spawn_role_creep(&self, role: &dyn Role) -> ReturnCode {
+        self.spawn_job_creep(&role.body(self.room_energy_capacity), Job::new(role.name()))
+    }
+
     fn spawn_creep(&self, body: &[Part], name: &str) -> ReturnCode {
-        self._source.spawn_creep(body, name)
+        api().spawn_creep(&self.name, body, name, &[])
     }
 }
@@ -0,0 +1,169 @@
+use crate::data::{body_cost, Job};
+use crate::tasks::Task;
+use screeps::Part;
+
+/// A creep we want spawned in a room, waiting for a free spawn and enough energy.
+#[derive(Debug, Clone)]
+pub struct SpawnRequest {
+    job: Job,
+    body: Vec<Part>,
+    priority: u32,
+    memory: Vec<(String, serde_json::Value)>,
+    deadline: Option<u32>,
+}
+
+impl SpawnRequest {
+    pub fn new(job: Job, body: Vec<Part>) -> SpawnRequest {
+        SpawnRequest {
+            job,
+            body,
+            priority: 0,
+            memory: vec![],
+            deadline: None,
+        }
+    }
+
+    /// Higher priorities are spawned first.
+    pub fn priority(mut self, priority: u32) -> SpawnRequest {
+        self.priority = priority;
+        self
+    }
+
+    /// Drops the request if it wasn't spawned by game tick `deadline`.
+    pub fn deadline(mut self, deadline: u32) -> SpawnRequest {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets `key` in the memory of the new creep.
+    pub fn memory(mut self, key: &str, value: serde_json::Value) -> SpawnRequest {
+        self.memory.push((key.to_string(), value));
+        self
+    }
+
+    /// Seeds the task queue of the new creep.
+    pub fn tasks(self, tasks: &[Task]) -> Result<SpawnRequest, serde_json::Error> {
+        let packed = serde_json::to_string(tasks)?;
+        Ok(self.memory("tasks", serde_json::Value::String(packed)))
+    }
+
+    pub fn job(&self) -> &Job {
+        &self.job
+    }
+
+    pub fn body(&self) -> &[Part] {
+        &self.body
+    }
+
+    pub fn memory_entries(&self) -> &[(String, serde_json::Value)] {
+        &self.memory
+    }
+
+    pub fn cost(&self) -> u32 {
+        body_cost(&self.body)
+    }
+
+    fn is_expired(&self, time: u32) -> bool {
+        self.deadline.is_some_and(|deadline| time > deadline)
+    }
+}
+
+/// Pending spawn requests of a room, ordered by priority and then by age.
+#[derive(Default)]
+pub struct SpawnQueue {
+    requests: Vec<SpawnRequest>,
+}
+
+impl SpawnQueue {
+    pub fn new() -> SpawnQueue {
+        SpawnQueue { requests: vec![] }
+    }
+
+    pub fn push(&mut self, request: SpawnRequest) {
+        let index = self
+            .requests
+            .iter()
+            .position(|queued| queued.priority < request.priority)
+            .unwrap_or(self.requests.len());
+        self.requests.insert(index, request);
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SpawnRequest> {
+        self.requests.iter()
+    }
+
+    /// Drops requests whose deadline passed.
+    pub fn expire(&mut self, time: u32) {
+        self.requests.retain(|request| {
+            if request.is_expired(time) {
+                info!("dropping expired spawn request for {}", request.job);
+                return false;
+            }
+            true
+        });
+    }
+
+    /// The most important request a room with `energy_capacity` can ever pay for.
+    pub fn next(&self, energy_capacity: u32) -> Option<usize> {
+        self.requests
+            .iter()
+            .position(|request| request.cost() <= energy_capacity)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&SpawnRequest> {
+        self.requests.get(index)
+    }
+
+    pub fn remove(&mut self, index: usize) -> SpawnRequest {
+        self.requests.remove(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(name: &str, priority: u32, body: Vec<Part>) -> SpawnRequest {
+        SpawnRequest::new(Job::new(name), body).priority(priority)
+    }
+
+    #[test]
+    fn requests_are_ordered_by_priority_then_age() {
+        let mut queue = SpawnQueue::new();
+        queue.push(request("Hauler", 1, vec![Part::Move]));
+        queue.push(request("Miner", 5, vec![Part::Move]));
+        queue.push(request("Upgrader", 1, vec![Part::Move]));
+        queue.push(request("Defender", 9, vec![Part::Move]));
+
+        let order: Vec<&str> = queue.iter().map(|r| r.job().as_str()).collect();
+        assert_eq!(order, vec!["Defender", "Miner", "Hauler", "Upgrader"]);
+    }
+
+    #[test]
+    fn unaffordable_and_expired_requests_are_skipped() {
+        let mut queue = SpawnQueue::new();
+        queue.push(request("Claimer", 9, vec![Part::Claim, Part::Move]).deadline(100));
+        queue.push(request(
+            "Miner",
+            5,
+            vec![Part::Work, Part::Work, Part::Move],
+        ));
+
+        assert_eq!(queue.get(0).unwrap().cost(), 650);
+        assert_eq!(queue.next(300), Some(1));
+        assert_eq!(queue.next(800), Some(0));
+
+        queue.expire(101);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.next(300), Some(0));
+        assert_eq!(queue.next(200), None);
+    }
+}
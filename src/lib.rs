@@ -5,9 +5,7 @@ extern crate log;
 extern crate lazy_static;
 
 use crate::data::Game;
-use crate::lazy_static::__Deref;
 use crate::tasks::{Harvest, Task, TaskTrait};
-use std::collections::HashSet;
 use std::sync::{Mutex, MutexGuard};
 use stdweb::js;
 
@@ -19,9 +17,12 @@ mod tests {
     }
 }
 
+pub mod api;
 pub mod data;
 pub mod logging;
+pub mod roles;
 pub mod tasks;
+pub mod travel;
 
 lazy_static! {
     static ref GAME: Mutex<Game> = Mutex::new(Game::new());
@@ -42,43 +43,21 @@ fn test_serde() -> Result<String, serde_json::Error> {
     Ok(unpacked.name().to_string())
 }
 
-pub fn init_screeps_connection(game_loop: &'static dyn Fn(&Game)) {
-    std::panic::set_hook(Box::new(|info| {
-        let msg = &info.to_string();
-        let panic_message = msg.to_owned();
-        /*
-        In case this might be useful
-        // Add the error stack to our message.
-        //
-        // This ensures that even if the `console` implementation doesn't
-        // include stacks for `console.error`, the stack is still available
-        // for the user. Additionally, Firefox's console tries to clean up
-        // stack traces, and ruins Rust symbols in the process
-        // (https://bugzilla.mozilla.org/show_bug.cgi?id=1519569) but since
-        // it only touches the logged message's associated stack, and not
-        // the message's contents, by including the stack in the message
-        // contents we make sure it is available to the user.
-        msg.push_str("\n\nStack:\n\n");
-        let e = Error::new();
-        let stack = e.stack();
-        msg.push_str(&stack);
-
-        // Safari's devtools, on the other hand, _do_ mess with logged
-        // messages' contents, so we attempt to break their heuristics for
-        // doing that by appending some whitespace.
-        // https://github.com/rustwasm/console_error_panic_hook/issues/7
-        msg.push_str("\n\n");
-        */
-        js! { @(no_return)
-            console.error( @{msg} );
-        }
-        panic!(panic_message);
-    }));
+pub fn init_screeps_connection(game_loop: &'static dyn Fn(&mut Game)) {
+    logging::install_panic_hook();
     logging::setup_logging(logging::Info);
     info!(
         "Global Reset! Compile took: {}",
         screeps::game::cpu::get_used()
     );
+    let panics = logging::panic_count();
+    if panics > 0 {
+        warn!(
+            "version {} panicked {} times so far",
+            logging::VERSION,
+            panics
+        );
+    }
 
     match test_serde() {
         Ok(thetask) => info!("{}", thetask),
@@ -86,18 +65,13 @@ pub fn init_screeps_connection(game_loop: &'static dyn Fn(&Game)) {
     }
 
     let _game_loop = move || {
+        logging::refresh_levels();
         debug!("loop starting! CPU: {}", screeps::game::cpu::get_used());
 
-        game().refresh_state();
-
-        game_loop(game().deref());
-
-        let time = screeps::game::time();
+        game().tick(game_loop);
 
-        if time % 128 == 3 {
-            info!("running memory cleanup");
-            cleanup_memory().expect("expected Memory.creeps format to be a regular memory object");
-        }
+        logging::flush_notifications();
+        logging::write_log_buffer(false);
 
         debug!("done! cpu: {}", screeps::game::cpu::get_used())
     };
@@ -106,6 +80,12 @@ pub fn init_screeps_connection(game_loop: &'static dyn Fn(&Game)) {
         // show more error frames in js (default: 10)
         Error.stackTraceLimit = 25;
 
+        // `dump_logs()` in the console prints the lines logged lately
+        var dump_logs = @{logging::dump_logs};
+        global.dump_logs = function() {
+            console.log(dump_logs());
+        };
+
         var game_loop = @{_game_loop};
 
         module.exports.loop = function() {
@@ -131,24 +111,3 @@ pub fn init_screeps_connection(game_loop: &'static dyn Fn(&Game)) {
         }
     }
 }
-
-fn cleanup_memory() -> Result<(), Box<dyn (::std::error::Error)>> {
-    let alive_creeps: HashSet<String> = screeps::game::creeps::keys().into_iter().collect();
-
-    let screeps_memory = match screeps::memory::root().dict("creeps")? {
-        Some(v) => v,
-        None => {
-            warn!("not cleaning game creep memory: no Memory.creeps dict");
-            return Ok(());
-        }
-    };
-
-    for mem_name in screeps_memory.keys() {
-        if !alive_creeps.contains(&mem_name) {
-            debug!("cleaning up creep memory of dead creep {}", mem_name);
-            screeps_memory.del(&mem_name);
-        }
-    }
-
-    Ok(())
-}
@@ -0,0 +1,188 @@
+use crate::api::{activate_segment, api};
+use std::collections::VecDeque;
+
+/// Most a segment can hold.
+const SEGMENT_SIZE: usize = 100 * 1024;
+
+/// Where and how much of the log is kept, see [`LogBuffer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BufferConfig {
+    /// Lines kept, the oldest ones make way for new ones.
+    pub capacity: usize,
+    /// `RawMemory` segment the lines are written to.
+    pub segment: u32,
+    /// Ticks between writes of the segment.
+    pub interval: u32,
+}
+
+impl Default for BufferConfig {
+    fn default() -> BufferConfig {
+        BufferConfig {
+            capacity: 500,
+            segment: 1,
+            interval: 20,
+        }
+    }
+}
+
+/// The last lines logged, written into a segment every few ticks so they survive resets.
+pub struct LogBuffer {
+    config: BufferConfig,
+    lines: VecDeque<String>,
+    changed: bool,
+    loaded: bool,
+    saved_at: u32,
+}
+
+impl LogBuffer {
+    pub fn new(config: BufferConfig) -> LogBuffer {
+        LogBuffer {
+            config,
+            lines: VecDeque::with_capacity(config.capacity),
+            changed: false,
+            loaded: false,
+            saved_at: 0,
+        }
+    }
+
+    pub fn config(&self) -> BufferConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: BufferConfig) {
+        self.config = config;
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        while self.lines.len() > self.config.capacity {
+            self.lines.pop_front();
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        self.lines.push_back(line);
+        self.truncate();
+        self.changed = true;
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    /// All lines, oldest first, one per line.
+    pub fn dump(&self) -> String {
+        self.lines().collect::<Vec<_>>().join("\n")
+    }
+
+    /// Puts the lines read from the segment in front of those logged since.
+    pub fn restore(&mut self, saved: &str) -> Result<usize, serde_json::Error> {
+        let saved: Vec<String> = serde_json::from_str(saved)?;
+        let count = saved.len();
+        for line in saved.into_iter().rev() {
+            self.lines.push_front(line);
+        }
+        self.truncate();
+        Ok(count)
+    }
+
+    /// The lines as they're stored in the segment, leaving out the oldest ones that don't fit.
+    pub fn to_segment(&self) -> String {
+        let mut skip = 0;
+        loop {
+            let lines: Vec<&str> = self.lines().skip(skip).collect();
+            let data = serde_json::to_string(&lines).unwrap_or_default();
+            if data.len() <= SEGMENT_SIZE || lines.is_empty() {
+                return data;
+            }
+            // roughly as many as are too many, at least one
+            skip += ((data.len() - SEGMENT_SIZE) * lines.len() / data.len()).max(1);
+        }
+    }
+
+    /// Whether the segment has been read, see [`restore`](LogBuffer::restore).
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    /// Reads the lines the last global saved, once the segment is readable, which is no
+    /// earlier than the tick after it was activated. Returns how many lines were restored.
+    pub fn load(&mut self) -> Option<Result<usize, serde_json::Error>> {
+        activate_segment(self.config.segment);
+        let saved = api().segment(self.config.segment)?;
+        self.loaded = true;
+        if saved.is_empty() {
+            return Some(Ok(0));
+        }
+        Some(self.restore(&saved))
+    }
+
+    /// What to write into the segment at `tick`, if anything changed and it's been long enough
+    /// since it was last written to. Nothing is written before the saved lines were loaded.
+    pub fn save(&mut self, tick: u32, force: bool) -> Option<String> {
+        let due = force || tick >= self.saved_at + self.config.interval;
+        if !self.loaded || !self.changed || !due {
+            return None;
+        }
+        self.changed = false;
+        self.saved_at = tick;
+        Some(self.to_segment())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::MockApi;
+
+    #[test]
+    fn old_lines_make_way_and_survive_resets() {
+        let config = BufferConfig {
+            capacity: 3,
+            segment: 1,
+            interval: 10,
+        };
+        let mut buffer = LogBuffer::new(config);
+        buffer.loaded = true;
+        for line in &["a", "b", "c", "d"] {
+            buffer.push(line.to_string());
+        }
+        assert_eq!(buffer.dump(), "b\nc\nd");
+        assert_eq!(buffer.save(5, false), None);
+        let saved = buffer.save(10, false).unwrap();
+        assert_eq!(saved, r#"["b","c","d"]"#);
+        assert_eq!(buffer.save(30, false), None);
+
+        let mut reset = LogBuffer::new(config);
+        reset.push("e".to_string());
+        assert_eq!(reset.restore(&saved).unwrap(), 3);
+        assert_eq!(reset.dump(), "c\nd\ne");
+
+        let mut huge = LogBuffer::new(BufferConfig {
+            capacity: 1000,
+            ..config
+        });
+        for _ in 0..1000 {
+            huge.push("x".repeat(200));
+        }
+        assert!(huge.to_segment().len() <= SEGMENT_SIZE);
+    }
+
+    #[test]
+    fn nothing_is_saved_before_the_last_lines_are_read() {
+        let mock = MockApi::install();
+        mock.world_mut()
+            .segments
+            .insert(1, r#"["a","b"]"#.to_string());
+        let mut buffer = LogBuffer::new(BufferConfig::default());
+        buffer.push("c".to_string());
+
+        // the segment was only just activated
+        assert!(buffer.load().is_none());
+        assert_eq!(buffer.save(0, true), None);
+
+        mock.world_mut().time += 1;
+        assert_eq!(buffer.load().unwrap().unwrap(), 2);
+        assert_eq!(buffer.save(1, true).unwrap(), r#"["a","b","c"]"#);
+    }
+}
@@ -0,0 +1,174 @@
+use log::Level;
+use screeps::RoomName;
+use std::cell::RefCell;
+use std::fmt::{self, Write};
+
+/// What the bot is busy with, attached to everything logged meanwhile, see [`with_context`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogContext {
+    pub room: Option<RoomName>,
+    pub creep: Option<String>,
+    pub task: Option<String>,
+}
+
+impl LogContext {
+    pub fn room(room: RoomName) -> LogContext {
+        LogContext {
+            room: Some(room),
+            creep: None,
+            task: None,
+        }
+    }
+
+    pub fn creep(creep: &str, room: RoomName) -> LogContext {
+        LogContext {
+            room: Some(room),
+            creep: Some(creep.to_string()),
+            task: None,
+        }
+    }
+
+    /// The same context, with `task` being worked on.
+    pub fn with_task(&self, task: &str) -> LogContext {
+        LogContext {
+            task: Some(task.to_string()),
+            ..self.clone()
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.room.is_none() && self.creep.is_none() && self.task.is_none()
+    }
+
+    // creep and task, what's left of the context after the room
+    fn names(&self) -> Vec<&str> {
+        self.creep
+            .iter()
+            .chain(self.task.iter())
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+impl fmt::Display for LogContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let room = self.room.map(|room| room.to_string());
+        let parts: Vec<&str> = room
+            .iter()
+            .map(String::as_str)
+            .chain(self.names())
+            .collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Vec<LogContext>> = const { RefCell::new(vec![]) };
+}
+
+/// Runs `f` with `context` attached to what it logs. Contexts nest, the innermost one wins.
+pub fn with_context<R>(context: LogContext, f: impl FnOnce() -> R) -> R {
+    CONTEXT.with(|stack| stack.borrow_mut().push(context));
+    let result = f();
+    CONTEXT.with(|stack| stack.borrow_mut().pop());
+    result
+}
+
+/// The context `with_context` set up, if any.
+pub fn current_context() -> Option<LogContext> {
+    CONTEXT.with(|stack| stack.borrow().last().cloned())
+}
+
+fn color(level: Level) -> &'static str {
+    match level {
+        Level::Error => "#ff5f5f",
+        Level::Warn => "#ffaf00",
+        Level::Info => "#d7d7d7",
+        Level::Debug => "#87afd7",
+        Level::Trace => "#808080",
+    }
+}
+
+/// Keeps messages from being taken for markup by the console.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Where a record was logged from, for [`format`].
+pub struct Origin<'a> {
+    pub tick: u32,
+    pub cpu: f64,
+    pub shard: &'a str,
+    pub context: Option<&'a LogContext>,
+}
+
+/// A line for the in-game console: tick and CPU used so far, the level in its colour, the
+/// target, and the room as a link to it along with the creep and task, if there's a context.
+pub fn format(level: Level, target: &str, message: &str, origin: &Origin<'_>) -> String {
+    let mut line = format!(
+        "<span style=\"color:#808080\">[{} {:5.2}]</span> <span style=\"color:{}\">{:5}</span> {}",
+        origin.tick,
+        origin.cpu,
+        color(level),
+        level,
+        target
+    );
+    if let Some(context) = origin.context.filter(|context| !context.is_empty()) {
+        line.push_str(" [");
+        if let Some(room) = context.room {
+            let _ = write!(
+                line,
+                "<a href=\"#!/room/{}/{}\">{}</a>",
+                origin.shard, room, room
+            );
+        }
+        let names = context.names();
+        if context.room.is_some() && !names.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&escape(&names.join(" ")));
+        line.push(']');
+    }
+    let _ = write!(
+        line,
+        ": <span style=\"color:{}\">{}</span>",
+        color(level),
+        escape(message)
+    );
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::room_name;
+
+    #[test]
+    fn lines_carry_tick_cpu_and_the_innermost_context() {
+        let room = room_name();
+        let context = with_context(LogContext::room(room), || {
+            let creep = LogContext::creep("Harvester:1", room);
+            with_context(creep.with_task("Harvest"), current_context)
+        });
+        assert_eq!(current_context(), None);
+        assert_eq!(
+            context.as_ref().unwrap().to_string(),
+            "W1N1 Harvester:1 Harvest"
+        );
+        let origin = Origin {
+            tick: 1234,
+            cpu: 3.5,
+            shard: "shard3",
+            context: context.as_ref(),
+        };
+        assert_eq!(
+            format(Level::Warn, "rusty_screeps::tasks", "1 < 2", &origin),
+            "<span style=\"color:#808080\">[1234  3.50]</span> \
+             <span style=\"color:#ffaf00\">WARN </span> rusty_screeps::tasks \
+             [<a href=\"#!/room/shard3/W1N1\">W1N1</a> Harvester:1 Harvest]: \
+             <span style=\"color:#ffaf00\">1 &lt; 2</span>"
+        );
+    }
+}
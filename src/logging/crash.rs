@@ -0,0 +1,77 @@
+use crate::logging::LogContext;
+use serde::Serialize;
+
+/// `RUSTY_SCREEPS_BUILD` as set by the deploy, or else the crate version.
+pub const VERSION: &str = match option_env!("RUSTY_SCREEPS_BUILD") {
+    Some(build) => build,
+    None => env!("CARGO_PKG_VERSION"),
+};
+
+/// Lines from the log buffer kept in a crash report.
+pub const CRASH_LOG_LINES: usize = 20;
+
+/// What we know about the last panic, stored in `Memory.crash`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CrashReport {
+    pub tick: u32,
+    pub version: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creep: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// The last lines logged before the panic, oldest first.
+    pub log: Vec<String>,
+}
+
+impl CrashReport {
+    pub fn new(
+        tick: u32,
+        message: String,
+        location: Option<String>,
+        context: Option<LogContext>,
+        log: Vec<String>,
+    ) -> CrashReport {
+        let context = context.unwrap_or_default();
+        CrashReport {
+            tick,
+            version: VERSION,
+            message,
+            location,
+            room: context.room.map(|room| room.to_string()),
+            creep: context.creep,
+            task: context.task,
+            log,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::room_name;
+
+    #[test]
+    fn reports_name_what_was_running() {
+        let context = LogContext::creep("Harvester:1", room_name()).with_task("Harvest");
+        let report = CrashReport::new(
+            42,
+            "index out of bounds".to_string(),
+            Some("src/tasks/harvest.rs:10:5".to_string()),
+            Some(context),
+            vec!["[41] INFO  rusty_screeps: hi".to_string()],
+        );
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["room"], "W1N1");
+        assert_eq!(json["task"], "Harvest");
+        assert_eq!(json["version"], VERSION);
+
+        let report = CrashReport::new(42, "oops".to_string(), None, None, vec![]);
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("creep") && !json.contains("location"));
+    }
+}
@@ -0,0 +1,434 @@
+mod buffer;
+mod console;
+mod crash;
+mod notify;
+
+pub use self::buffer::{BufferConfig, LogBuffer};
+pub use self::console::{current_context, with_context, LogContext};
+pub use self::crash::{CrashReport, CRASH_LOG_LINES, VERSION};
+pub use self::notify::{Notifications, NotifyConfig};
+
+use crate::api::api;
+
+use log::LevelFilter;
+pub use log::LevelFilter::*;
+use std::str::FromStr;
+use std::sync::Mutex;
+use stdweb::js;
+use stdweb::unstable::TryInto;
+struct JsLog;
+struct JsNotify;
+struct JsBuffer;
+
+/// Levels to log at, per target, like `rusty_screeps::tasks=debug`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLevels {
+    default: LevelFilter,
+    // the longest target matching a record decides
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl LogLevels {
+    pub fn new(default: LevelFilter) -> LogLevels {
+        LogLevels {
+            default,
+            targets: vec![],
+        }
+    }
+
+    /// Reads `Memory.logging`, an object from targets to levels or a string like
+    /// `warn,rusty_screeps::tasks=debug`.
+    pub fn parse(config: &str, default: LevelFilter) -> Result<LogLevels, String> {
+        let config: serde_json::Value = serde_json::from_str(config).map_err(|e| e.to_string())?;
+        let entries: Vec<(String, String)> = match config {
+            serde_json::Value::String(spec) => spec
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| match entry.find('=') {
+                    Some(index) => (entry[..index].to_string(), entry[index + 1..].to_string()),
+                    None => ("default".to_string(), entry.to_string()),
+                })
+                .collect(),
+            serde_json::Value::Object(targets) => targets
+                .into_iter()
+                .map(|(target, level)| match level {
+                    serde_json::Value::String(level) => Ok((target, level)),
+                    level => Err(format!("level of {} is not a string: {}", target, level)),
+                })
+                .collect::<Result<_, _>>()?,
+            config => return Err(format!("expected an object or a string, got {}", config)),
+        };
+
+        let mut levels = LogLevels::new(default);
+        for (target, level) in entries {
+            let level = LevelFilter::from_str(level.trim())
+                .map_err(|_| format!("unknown level {} for {}", level, target))?;
+            match target.trim() {
+                "default" | "" => levels.default = level,
+                target => levels.targets.push((target.to_string(), level)),
+            }
+        }
+        levels
+            .targets
+            .sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Ok(levels)
+    }
+
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(prefix, _)| {
+                target == prefix
+                    || (target.starts_with(prefix.as_str())
+                        && target[prefix.len()..].starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    pub fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    /// The most verbose level of any target, anything above it can be skipped right away.
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+struct Levels {
+    levels: LogLevels,
+    // passed to `setup_logging`, for whatever `Memory.logging` leaves out
+    default: LevelFilter,
+    // `Memory.logging` the levels were read from
+    config: Option<String>,
+}
+
+lazy_static! {
+    static ref LEVELS: Mutex<Levels> = Mutex::new(Levels {
+        levels: LogLevels::new(Info),
+        default: Info,
+        config: None,
+    });
+    static ref NOTIFICATIONS: Mutex<Notifications> =
+        Mutex::new(Notifications::new(NotifyConfig::default()));
+    static ref SHARD: String = screeps::game::shards::name();
+    static ref BUFFER: Mutex<LogBuffer> = Mutex::new(LogBuffer::new(BufferConfig::default()));
+}
+
+// logging from the panic hook mustn't wait on a lock the panic left held, what's logged then
+// is dropped
+fn enabled(metadata: &log::Metadata<'_>) -> bool {
+    match LEVELS.try_lock() {
+        Ok(levels) => levels.levels.enabled(metadata),
+        Err(_) => false,
+    }
+}
+
+/// Re-reads the log levels from `Memory.logging` if it changed since the last time, so they
+/// can be set from the console without deploying.
+pub fn refresh_levels() {
+    let config: Option<String> = js! {
+        return Memory.logging === undefined ? null : JSON.stringify(Memory.logging);
+    }
+    .try_into()
+    .unwrap_or(None);
+
+    let mut levels = LEVELS.lock().unwrap();
+    if config == levels.config {
+        return;
+    }
+    levels.config = config.clone();
+    let parsed = match config {
+        Some(config) => LogLevels::parse(&config, levels.default),
+        None => Ok(LogLevels::new(levels.default)),
+    };
+    let result = parsed.map(|parsed| {
+        levels.levels = parsed;
+        log::set_max_level(levels.levels.max_level());
+        levels.levels.clone()
+    });
+    // logging takes the lock as well
+    drop(levels);
+    match result {
+        Ok(levels) => info!("log levels are now {:?}", levels),
+        Err(error) => warn!("ignoring Memory.logging: {}", error),
+    }
+}
+
+impl log::Log for JsLog {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        enabled(metadata)
+    }
+    fn log(&self, record: &log::Record<'_>) {
+        let message = format!("{}", record.args());
+        js! {
+            console.log(@{message});
+        }
+    }
+    fn flush(&self) {}
+}
+fn notify(message: &str, time: u32) {
+    let message = format!("[{}] {}", time, message);
+    js! { @(no_return)
+        Game.notify(@{message});
+    }
+}
+
+fn now() -> u64 {
+    let now: f64 = js!(return Date.now();).try_into().unwrap_or(0.0);
+    now as u64
+}
+
+impl log::Log for JsNotify {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+    fn log(&self, record: &log::Record<'_>) {
+        let message = format!("{}", record.args());
+        let time = screeps::game::time();
+        let send = match NOTIFICATIONS.try_lock() {
+            Ok(mut notifications) => notifications.record(&message, time, now()),
+            Err(_) => None,
+        };
+        if let Some(message) = send {
+            notify(&message, time);
+        }
+    }
+    fn flush(&self) {}
+}
+
+/// Changes how often warnings and errors may be sent as notifications.
+pub fn configure_notifications(config: NotifyConfig) {
+    NOTIFICATIONS.lock().unwrap().set_config(config);
+}
+
+/// Sends digests of the notifications that were held back, meant to run once per tick.
+pub fn flush_notifications() {
+    let time = screeps::game::time();
+    let digests = NOTIFICATIONS.lock().unwrap().flush(time, now());
+    for digest in digests {
+        notify(&digest, time);
+    }
+}
+
+impl log::Log for JsBuffer {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+    fn log(&self, record: &log::Record<'_>) {
+        let context = current_context()
+            .map(|context| format!(" [{}]", context))
+            .unwrap_or_default();
+        let line = format!(
+            "[{}] {:5} {}{}: {}",
+            screeps::game::time(),
+            record.level(),
+            record.target(),
+            context,
+            record.args()
+        );
+        if let Ok(mut buffer) = BUFFER.try_lock() {
+            buffer.push(line);
+        }
+    }
+    fn flush(&self) {}
+}
+
+/// Changes how many lines are kept and which segment they go into.
+pub fn configure_log_buffer(config: BufferConfig) {
+    BUFFER.lock().unwrap().set_config(config);
+}
+
+/// Writes the last lines logged into their segment if it's time to, or right away if `force`
+/// is set. Meant to run once per tick.
+pub fn write_log_buffer(force: bool) {
+    let api = api();
+    // a panic while logging leaves the buffer locked
+    let mut buffer = match BUFFER.try_lock() {
+        Ok(buffer) => buffer,
+        Err(_) => return,
+    };
+    let segment = buffer.config().segment;
+    let mut restored = None;
+    if !buffer.is_loaded() {
+        restored = buffer.load();
+    }
+    if let Some(data) = buffer.save(api.time(), force) {
+        api.set_segment(segment, &data);
+    }
+    // logging takes the lock as well
+    drop(buffer);
+    match restored {
+        Some(Ok(count)) if count > 0 => {
+            info!("restored {} log lines from segment {}", count, segment)
+        }
+        Some(Err(error)) => warn!(
+            "could not read log lines from segment {}: {}",
+            segment, error
+        ),
+        _ => {}
+    }
+}
+
+/// The last lines logged, for the console's `dump_logs()`.
+pub fn dump_logs() -> String {
+    BUFFER.lock().unwrap().dump()
+}
+
+fn last_log_lines(count: usize) -> Vec<String> {
+    match BUFFER.try_lock() {
+        Ok(buffer) => {
+            let lines: Vec<&str> = buffer.lines().collect();
+            let start = lines.len().saturating_sub(count);
+            lines[start..].iter().map(|line| line.to_string()).collect()
+        }
+        Err(_) => vec![],
+    }
+}
+
+/// Panics of this [`VERSION`] so far, a number going up after a deploy means it's crashing
+/// over and over.
+pub fn panic_count() -> u32 {
+    let count: f64 = js! {
+        var panics = Memory.panics;
+        return (panics && panics[@{VERSION}]) || 0;
+    }
+    .try_into()
+    .unwrap_or(0.0);
+    count as u32
+}
+
+/// Stores `report` in `Memory.crash` and counts the panic in `Memory.panics[VERSION]`.
+fn record_crash(report: &CrashReport) {
+    let report = match serde_json::to_string(report) {
+        Ok(report) => report,
+        Err(_) => return,
+    };
+    js! { @(no_return)
+        Memory.crash = JSON.parse(@{report});
+        var panics = Memory.panics || (Memory.panics = {});
+        panics[@{VERSION}] = (panics[@{VERSION}] || 0) + 1;
+    }
+}
+
+/// Logs panics along with the JS stack, and leaves a [`CrashReport`] in `Memory.crash` with
+/// the last lines logged, which are written to their segment as well.
+pub fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => info.to_string(),
+            },
+        };
+        let location = info.location().map(|location| location.to_string());
+        let report = CrashReport::new(
+            screeps::game::time(),
+            message,
+            location,
+            current_context(),
+            last_log_lines(CRASH_LOG_LINES),
+        );
+        record_crash(&report);
+        write_log_buffer(true);
+
+        let mut msg = info.to_string();
+        // Add the error stack to our message.
+        //
+        // This ensures that even if the `console` implementation doesn't
+        // include stacks for `console.error`, the stack is still available
+        // for the user. Additionally, Firefox's console tries to clean up
+        // stack traces, and ruins Rust symbols in the process
+        // (https://bugzilla.mozilla.org/show_bug.cgi?id=1519569) but since
+        // it only touches the logged message's associated stack, and not
+        // the message's contents, by including the stack in the message
+        // contents we make sure it is available to the user.
+        let stack: String = js!(return new Error().stack;)
+            .try_into()
+            .unwrap_or_default();
+        msg.push_str("\n\nStack:\n\n");
+        msg.push_str(&stack);
+        // Safari's devtools, on the other hand, _do_ mess with logged
+        // messages' contents, so we attempt to break their heuristics for
+        // doing that by appending some whitespace.
+        // https://github.com/rustwasm/console_error_panic_hook/issues/7
+        msg.push_str("\n\n");
+        js! { @(no_return)
+            console.error( @{&msg} );
+        }
+        panic!("{}", msg);
+    }));
+}
+
+/// Logs to the console at `verbosity` or the levels in `Memory.logging`, notifying about
+/// warnings and errors and keeping the last lines around.
+pub fn setup_logging(verbosity: log::LevelFilter) {
+    {
+        let mut levels = LEVELS.lock().unwrap();
+        levels.default = verbosity;
+        levels.levels = LogLevels::new(verbosity);
+    }
+    fern::Dispatch::new()
+        .level(log::LevelFilter::Trace)
+        .filter(enabled)
+        .chain(
+            fern::Dispatch::new()
+                .format(|out, message, record| {
+                    let context = current_context();
+                    let origin = console::Origin {
+                        tick: screeps::game::time(),
+                        cpu: screeps::game::cpu::get_used(),
+                        shard: &SHARD,
+                        context: context.as_ref(),
+                    };
+                    let message = message.to_string();
+                    let line = console::format(record.level(), record.target(), &message, &origin);
+                    out.finish(format_args!("{}", line))
+                })
+                .chain(Box::new(JsLog) as Box<dyn log::Log>),
+        )
+        .chain(
+            fern::Dispatch::new()
+                .level(log::LevelFilter::Warn)
+                .format(|out, message, record| {
+                    out.finish(format_args!("{}: {}", record.target(), message))
+                })
+                .chain(Box::new(JsNotify) as Box<dyn log::Log>),
+        )
+        .chain(Box::new(JsBuffer) as Box<dyn log::Log>)
+        .apply()
+        .expect("expected setup_logging to only ever be called once per instance");
+    log::set_max_level(verbosity);
+    refresh_levels();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_most_specific_target_decides() {
+        let config = r#"{"default": "warn", "rusty_screeps::tasks": "debug",
+            "rusty_screeps::tasks::harvest": "error"}"#;
+        let levels = LogLevels::parse(config, Info).unwrap();
+        assert_eq!(levels.level_for("rusty_screeps::data::game"), Warn);
+        assert_eq!(levels.level_for("rusty_screeps::tasks"), Debug);
+        assert_eq!(levels.level_for("rusty_screeps::tasks::fill"), Debug);
+        assert_eq!(levels.level_for("rusty_screeps::tasks::harvest"), Error);
+        assert_eq!(levels.level_for("rusty_screeps::tasksx"), Warn);
+        assert_eq!(levels.max_level(), Debug);
+
+        let spec = r#""rusty_screeps::data::game=warn, trace""#;
+        let levels = LogLevels::parse(spec, Info).unwrap();
+        assert_eq!(levels.level_for("rusty_screeps::data::game::spawn"), Warn);
+        assert_eq!(levels.level_for("rusty_screeps::roles"), Trace);
+
+        assert!(LogLevels::parse(r#"{"rusty_screeps": "loud"}"#, Info).is_err());
+        assert!(LogLevels::parse("3", Info).is_err());
+    }
+}
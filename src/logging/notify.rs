@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+/// Distinct messages kept track of at once, any more are counted together as one.
+const MAX_SEEN: usize = 100;
+const OVERFLOW: &str = "other notifications";
+
+/// How often the same notification may be sent, and how many may be sent at all.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NotifyConfig {
+    /// Ticks during which repeats of a message are only counted, and then sent as a digest.
+    pub window: u32,
+    pub per_tick: u32,
+    pub per_day: u32,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> NotifyConfig {
+        NotifyConfig {
+            window: 1500,
+            per_tick: 2,
+            per_day: 40,
+        }
+    }
+}
+
+/// `message` with the words that have a digit in them, names, ids and positions mostly,
+/// replaced by `#`, so repeats of it count as the same.
+fn normalize(message: &str) -> String {
+    let mut normalized = String::with_capacity(message.len());
+    let mut word = String::new();
+    let end_word = |word: &mut String, normalized: &mut String| {
+        if word.chars().any(|c| c.is_ascii_digit()) {
+            normalized.push('#');
+        } else {
+            normalized.push_str(word);
+        }
+        word.clear();
+    };
+    for c in message.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            end_word(&mut word, &mut normalized);
+            normalized.push(c);
+        }
+    }
+    end_word(&mut word, &mut normalized);
+    normalized
+}
+
+struct Seen {
+    since: u32,
+    // times the message came up without being sent
+    count: u32,
+}
+
+/// Decides which warnings and errors are worth a `Game.notify`, repeats go out as a digest.
+pub struct Notifications {
+    config: NotifyConfig,
+    seen: BTreeMap<String, Seen>,
+    tick: u32,
+    sent_this_tick: u32,
+    day: u64,
+    sent_today: u32,
+}
+
+impl Notifications {
+    pub fn new(config: NotifyConfig) -> Notifications {
+        Notifications {
+            config,
+            seen: BTreeMap::new(),
+            tick: 0,
+            sent_this_tick: 0,
+            day: 0,
+            sent_today: 0,
+        }
+    }
+
+    pub fn set_config(&mut self, config: NotifyConfig) {
+        self.config = config;
+    }
+
+    /// Takes one notification out of the budgets, `now` being milliseconds since the epoch.
+    fn spend(&mut self, tick: u32, now: u64) -> bool {
+        if tick != self.tick {
+            self.tick = tick;
+            self.sent_this_tick = 0;
+        }
+        if now / DAY_MS != self.day {
+            self.day = now / DAY_MS;
+            self.sent_today = 0;
+        }
+        if self.sent_this_tick >= self.config.per_tick || self.sent_today >= self.config.per_day {
+            return false;
+        }
+        self.sent_this_tick += 1;
+        self.sent_today += 1;
+        true
+    }
+
+    /// Returns `message` if it should be sent now.
+    pub fn record(&mut self, message: &str, tick: u32, now: u64) -> Option<String> {
+        let mut key = normalize(message);
+        if !self.seen.contains_key(&key) && self.seen.len() >= MAX_SEEN {
+            key = OVERFLOW.to_string();
+        }
+        if let Some(seen) = self.seen.get_mut(&key) {
+            seen.count += 1;
+            return None;
+        }
+        let send = key != OVERFLOW && self.spend(tick, now);
+        let count = if send { 0 } else { 1 };
+        self.seen.insert(key, Seen { since: tick, count });
+        if send {
+            Some(message.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Digests of the messages whose window is over, as many as the budgets allow. The rest
+    /// wait for a later tick.
+    pub fn flush(&mut self, tick: u32, now: u64) -> Vec<String> {
+        let window = self.config.window;
+        let expired: Vec<String> = self
+            .seen
+            .iter()
+            .filter(|(_, seen)| tick >= seen.since + window)
+            .map(|(message, _)| message.clone())
+            .collect();
+        let mut digests = vec![];
+        for message in expired {
+            let seen = &self.seen[&message];
+            if seen.count == 0 {
+                self.seen.remove(&message);
+                continue;
+            }
+            if !self.spend(tick, now) {
+                break;
+            }
+            let seen = self.seen.remove(&message).unwrap();
+            digests.push(format!(
+                "{} occurred {} times since tick {}",
+                message, seen.count, seen.since
+            ));
+        }
+        digests
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_are_sent_as_digests_within_budget() {
+        let mut notifications = Notifications::new(NotifyConfig {
+            window: 10,
+            per_tick: 2,
+            per_day: 3,
+        });
+        let now = 5 * DAY_MS;
+        assert_eq!(
+            notifications.record("couldn't harvest", 100, now),
+            Some("couldn't harvest".to_string())
+        );
+        for tick in 101..105 {
+            assert_eq!(notifications.record("couldn't harvest", tick, now), None);
+        }
+        assert_eq!(
+            notifications.record("no path", 104, now),
+            Some("no path".to_string())
+        );
+        assert!(notifications.flush(105, now).is_empty());
+
+        // only one more fits into the day
+        assert_eq!(
+            notifications.flush(110, now),
+            vec!["couldn't harvest occurred 4 times since tick 100".to_string()]
+        );
+        assert_eq!(notifications.record("out of energy", 111, now), None);
+        assert!(notifications.flush(130, now).is_empty());
+
+        // the next day the message that didn't fit goes out with the count
+        assert_eq!(
+            notifications.flush(131, now + DAY_MS),
+            vec!["out of energy occurred 1 times since tick 111".to_string()]
+        );
+        assert_eq!(
+            notifications.record("couldn't harvest", 132, now + DAY_MS),
+            Some("couldn't harvest".to_string())
+        );
+    }
+
+    #[test]
+    fn messages_differing_in_names_are_counted_together_and_bounded() {
+        let mut notifications = Notifications::new(NotifyConfig::default());
+        assert_eq!(
+            normalize("Harvester:123 couldn't reach [room W1N1 pos 10,12]"),
+            "Harvester:# couldn't reach [room # pos #,#]"
+        );
+        let now = 5 * DAY_MS;
+        assert!(notifications.record("Miner:1 is lost", 1, now).is_some());
+        assert_eq!(notifications.record("Miner:2 is lost", 1, now), None);
+
+        for id in 0..1000 {
+            notifications.record(&format!("no path to {}", "x".repeat(id)), 2, now);
+        }
+        assert_eq!(notifications.seen.len(), MAX_SEEN + 1);
+        assert!(notifications.seen[OVERFLOW].count > 0);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::data::{BodyBuilder, Creep, Role, Room};
+use crate::tasks::{Fallback, Fill, FillOrder, Pickup, Task, Withdraw};
+use screeps::{Part, RoomName};
+use std::collections::HashMap;
+
+/// Keeps the spawns and extensions of its room topped up so spawning never waits on energy,
+/// fetching from containers and storage, or whatever lies around, between rounds.
+pub struct Filler {
+    population: usize,
+    order: FillOrder,
+}
+
+impl Filler {
+    pub fn new(population: usize) -> Filler {
+        Filler {
+            population,
+            order: FillOrder::Nearest,
+        }
+    }
+
+    /// Fills along the same path every time instead of always going to the closest target.
+    pub fn stable(mut self) -> Filler {
+        self.order = FillOrder::Stable;
+        self
+    }
+}
+
+impl Role for Filler {
+    fn name(&self) -> &str {
+        "Filler"
+    }
+
+    fn body(&self, energy: u32) -> Vec<Part> {
+        BodyBuilder::new(&[Part::Carry, Part::Carry, Part::Move])
+            .max_repeats(6)
+            .build(energy)
+            .unwrap_or_default()
+    }
+
+    fn population(&self) -> usize {
+        self.population
+    }
+
+    fn next_task(&self, creep: &Creep, _rooms: &HashMap<RoomName, Room>) -> Option<Task> {
+        if creep.energy() > 0 {
+            return Some(Fill::new(self.order).into());
+        }
+        Some(Fallback::new(vec![Withdraw::default().into(), Pickup::default().into()]).into())
+    }
+}
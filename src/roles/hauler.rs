@@ -0,0 +1,39 @@
+use crate::data::{BodyBuilder, Creep, Role, Room};
+use crate::tasks::Task;
+use screeps::{Part, RoomName};
+use std::collections::HashMap;
+
+/// Carries energy from where it lies to where it's needed, its work is handed out by
+/// `Game::run_logistics`.
+pub struct Hauler {
+    population: usize,
+}
+
+impl Hauler {
+    pub const NAME: &'static str = "Hauler";
+
+    pub fn new(population: usize) -> Hauler {
+        Hauler { population }
+    }
+}
+
+impl Role for Hauler {
+    fn name(&self) -> &str {
+        Hauler::NAME
+    }
+
+    fn body(&self, energy: u32) -> Vec<Part> {
+        BodyBuilder::new(&[Part::Carry, Part::Carry, Part::Move])
+            .max_repeats(8)
+            .build(energy)
+            .unwrap_or_default()
+    }
+
+    fn population(&self) -> usize {
+        self.population
+    }
+
+    fn next_task(&self, _creep: &Creep, _rooms: &HashMap<RoomName, Room>) -> Option<Task> {
+        None
+    }
+}
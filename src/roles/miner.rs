@@ -0,0 +1,85 @@
+use crate::data::{BodyBuilder, Creep, Role, Room};
+use crate::tasks::{Mine, Task};
+use screeps::{Part, RoomName, ENERGY_REGEN_TIME, HARVEST_POWER, SOURCE_ENERGY_CAPACITY};
+use std::collections::HashMap;
+
+/// WORK parts that drain a source of an owned room just as it regenerates.
+const MINER_WORK_PARTS: usize =
+    (SOURCE_ENERGY_CAPACITY / (HARVEST_POWER * ENERGY_REGEN_TIME)) as usize;
+
+/// Static miners, one for each source in the rooms we own, see `tasks::Mine`.
+pub struct Miner;
+
+impl Role for Miner {
+    fn name(&self) -> &str {
+        "Miner"
+    }
+
+    fn body(&self, energy: u32) -> Vec<Part> {
+        // the CARRY part lets it hand energy to links and work on its container
+        BodyBuilder::new(&[Part::Work])
+            .suffix(&[Part::Carry, Part::Move])
+            .max_repeats(MINER_WORK_PARTS)
+            .build(energy)
+            .unwrap_or_default()
+    }
+
+    fn population(&self) -> usize {
+        0
+    }
+
+    fn population_in(&self, rooms: &HashMap<RoomName, Room>) -> usize {
+        rooms
+            .values()
+            .filter(|room| room.is_mine())
+            .map(|room| room.sources().len())
+            .sum()
+    }
+
+    /// Mining the first source in the creep's room without a miner, none if they all have one.
+    fn next_task(&self, creep: &Creep, rooms: &HashMap<RoomName, Room>) -> Option<Task> {
+        let source = rooms
+            .get(creep.room_id())?
+            .sources()
+            .iter()
+            .find(|source| source.miner().is_none_or(|miner| miner == creep.name()))?;
+        Some(Mine::new(source.id().to_string()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{self, pos};
+    use crate::api::MockApi;
+    use screeps::Position;
+
+    #[test]
+    fn one_miner_per_source_in_owned_rooms() {
+        let mock = MockApi::install();
+        let other: RoomName = "W2N1".parse().unwrap();
+        {
+            let mut world = mock.world_mut();
+            world.add_controller(pos(25, 25));
+            world.add_source(pos(10, 10));
+            world.add_source(pos(40, 40));
+            world.add_source(Position::new(10, 10, other));
+        }
+        let mut rooms = fixtures::rooms();
+        rooms.insert(other, Room::from(other));
+
+        assert_eq!(Miner.population_in(&rooms), 2);
+        assert_eq!(
+            Miner.body(550),
+            vec![
+                Part::Work,
+                Part::Work,
+                Part::Work,
+                Part::Work,
+                Part::Carry,
+                Part::Move
+            ]
+        );
+        assert_eq!(Miner.body(2000).len(), MINER_WORK_PARTS + 2);
+    }
+}
@@ -0,0 +1,9 @@
+//! Roles the crate ships with, ready to be registered on `Game` next to the bot's own.
+
+mod filler;
+mod hauler;
+mod miner;
+
+pub use self::filler::Filler;
+pub use self::hauler::Hauler;
+pub use self::miner::Miner;
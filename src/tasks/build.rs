@@ -0,0 +1,77 @@
+use crate::api::api;
+use crate::data::{Creep, Reservations};
+use crate::tasks::task::TaskError::Invalid;
+use crate::tasks::task::{TaskContext, TaskOption, TaskResult};
+use crate::tasks::TaskTrait;
+use screeps::ReturnCode;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Build {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    _site_id: Option<String>,
+}
+
+impl Build {
+    pub fn new(site_id: String) -> Build {
+        Build {
+            _site_id: Some(site_id),
+        }
+    }
+
+    fn is_valid(&self, creep: &Creep) -> TaskResult {
+        if creep.energy() == 0 {
+            return Err(Invalid);
+        }
+        Ok(())
+    }
+}
+
+impl TaskTrait for Build {
+    fn name(&self) -> &str {
+        "Build"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        if self._site_id.is_none() {
+            let pos = *creep.pos();
+            let room = context.rooms.get(creep.room_id());
+            let reservations = &mut context.reservations;
+            let _site = room
+                .into_iter()
+                .flat_map(|room| room.construction_sites())
+                .filter(|site| site.my)
+                .filter(|site| {
+                    reservations.set_capacity(&site.id, site.progress_total - site.progress);
+                    reservations.remaining(&site.id).unwrap_or(0) > 0
+                })
+                .min_by_key(|site| pos.get_range_to(*site));
+            if let Some(site) = _site {
+                self._site_id = Some(site.id.clone());
+            }
+        }
+        self.reserve(creep, context.reservations);
+    }
+
+    fn execute(&mut self, creep: &Creep, _context: &mut TaskContext) -> TaskResult {
+        let site_id = &self._site_id.as_ref().ok_or_invalid()?;
+        // a finished site disappears, which ends the task here
+        let site = &api().get_construction_site(site_id).ok_or_invalid()?;
+        self.is_valid(creep)?;
+        if creep.pos().in_range_to(site, 3) {
+            let r = creep.build(site_id);
+            if r != ReturnCode::Ok {
+                warn!("couldn't build: {:?}", r);
+            }
+        } else {
+            creep.move_to(site);
+        }
+        Ok(())
+    }
+
+    fn reserve(&self, creep: &Creep, reservations: &mut Reservations) {
+        if let Some(site_id) = &self._site_id {
+            reservations.claim_available(site_id, creep.name(), creep.energy());
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::data::{Creep, Reservations};
+use crate::tasks::task::TaskError::Invalid;
+use crate::tasks::task::{TaskContext, TaskError, TaskResult};
+use crate::tasks::{Task, TaskTrait};
+use serde::{Deserialize, Serialize};
+
+fn is_done(result: &TaskResult) -> bool {
+    matches!(
+        result,
+        Err(TaskError::Invalid) | Err(TaskError::OptionFailed)
+    )
+}
+
+/// Runs its children one after another and is done after the last one finished.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Sequence {
+    children: Vec<Task>,
+    #[serde(default)]
+    index: usize,
+}
+
+impl Sequence {
+    pub fn new(children: Vec<Task>) -> Sequence {
+        Sequence { children, index: 0 }
+    }
+}
+
+impl TaskTrait for Sequence {
+    fn name(&self) -> &str {
+        "Sequence"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        if let Some(child) = self.children.get_mut(self.index) {
+            child.start(creep, context);
+        }
+    }
+
+    fn execute(&mut self, creep: &Creep, context: &mut TaskContext) -> TaskResult {
+        while let Some(child) = self.children.get_mut(self.index) {
+            let result = child.execute(creep, context);
+            if !is_done(&result) {
+                return result;
+            }
+            context.release_task(creep, child);
+            self.index += 1;
+            self.start(creep, context);
+        }
+        Err(Invalid)
+    }
+
+    fn reserve(&self, creep: &Creep, reservations: &mut Reservations) {
+        if let Some(child) = self.children.get(self.index) {
+            child.reserve(creep, reservations);
+        }
+    }
+}
+
+/// When a `Repeat` stops starting its child over.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Until {
+    Never,
+    CreepFull,
+    CreepEmpty,
+    Times(u32),
+}
+
+impl Until {
+    fn is_met(self, creep: &Creep, iterations: u32) -> bool {
+        match self {
+            Until::Never => false,
+            Until::CreepFull => creep.carry_total() == creep.carry_capacity(),
+            Until::CreepEmpty => creep.carry_total() == 0,
+            Until::Times(times) => iterations >= times,
+        }
+    }
+}
+
+/// Starts a fresh copy of its child every time the previous one finished, until the
+/// condition is met, or a fresh copy is done without ever running.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Repeat {
+    body: Box<Task>,
+    current: Box<Task>,
+    until: Until,
+    #[serde(default)]
+    iterations: u32,
+    #[serde(default)]
+    running: bool,
+}
+
+impl Repeat {
+    pub fn new(body: Task) -> Repeat {
+        Repeat {
+            current: Box::new(body.clone()),
+            body: Box::new(body),
+            until: Until::Never,
+            iterations: 0,
+            running: false,
+        }
+    }
+
+    pub fn until(mut self, until: Until) -> Repeat {
+        self.until = until;
+        self
+    }
+}
+
+impl TaskTrait for Repeat {
+    fn name(&self) -> &str {
+        "Repeat"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        self.current.start(creep, context);
+    }
+
+    fn execute(&mut self, creep: &Creep, context: &mut TaskContext) -> TaskResult {
+        if self.until.is_met(creep, self.iterations) {
+            return Err(Invalid);
+        }
+        let result = self.current.execute(creep, context);
+        if !is_done(&result) {
+            self.running |= result.is_ok();
+            return result;
+        }
+
+        context.release_task(creep, &self.current);
+        // with no target or nothing left to do, every copy would be done right away as well
+        if !self.running {
+            return Err(Invalid);
+        }
+        self.iterations += 1;
+        if self.until.is_met(creep, self.iterations) {
+            return Err(Invalid);
+        }
+        // the next round gets its first go next tick
+        *self.current = (*self.body).clone();
+        self.running = false;
+        self.current.start(creep, context);
+        Ok(())
+    }
+
+    fn reserve(&self, creep: &Creep, reservations: &mut Reservations) {
+        self.current.reserve(creep, reservations);
+    }
+}
+
+/// Tries its children in order until one of them gets going, and is done when that one is.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Fallback {
+    children: Vec<Task>,
+    #[serde(default)]
+    index: usize,
+    #[serde(default)]
+    running: bool,
+}
+
+impl Fallback {
+    pub fn new(children: Vec<Task>) -> Fallback {
+        Fallback {
+            children,
+            index: 0,
+            running: false,
+        }
+    }
+}
+
+impl TaskTrait for Fallback {
+    fn name(&self) -> &str {
+        "Fallback"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        if let Some(child) = self.children.get_mut(self.index) {
+            child.start(creep, context);
+        }
+    }
+
+    fn execute(&mut self, creep: &Creep, context: &mut TaskContext) -> TaskResult {
+        while let Some(child) = self.children.get_mut(self.index) {
+            match child.execute(creep, context) {
+                Ok(()) => {
+                    self.running = true;
+                    return Ok(());
+                }
+                result if self.running && is_done(&result) => return Err(Invalid),
+                // errors are retried by the runner like those of any other task
+                Err(TaskError::Error(error)) => return Err(TaskError::Error(error)),
+                _ => {
+                    context.release_task(creep, child);
+                    self.index += 1;
+                    self.start(creep, context);
+                }
+            }
+        }
+        Err(Invalid)
+    }
+
+    fn reserve(&self, creep: &Creep, reservations: &mut Reservations) {
+        if let Some(child) = self.children.get(self.index) {
+            child.reserve(creep, reservations);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{self, pos, TaskState};
+    use crate::api::MockApi;
+    use crate::tasks::{Harvest, Transfer};
+
+    #[test]
+    fn repeats_give_up_on_children_that_never_run() {
+        let mock = MockApi::install();
+        {
+            let mut world = mock.world_mut();
+            world.add_source(pos(12, 10));
+            world.add_creep("Worker:1", pos(11, 10), 50);
+        }
+        let creep = fixtures::creep("Worker:1");
+        let mut state = TaskState::default();
+        let mut context = state.context();
+
+        // the creep carries nothing, so there is nothing to transfer
+        let mut repeat = Repeat::new(Transfer::default().into());
+        repeat.start(&creep, &mut context);
+        assert!(matches!(repeat.execute(&creep, &mut context), Err(Invalid)));
+
+        let mut repeat = Repeat::new(Harvest::default().into());
+        repeat.start(&creep, &mut context);
+        assert!(repeat.execute(&creep, &mut context).is_ok());
+        assert!(repeat.running);
+    }
+}
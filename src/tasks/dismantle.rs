@@ -0,0 +1,77 @@
+use crate::api::api;
+use crate::data::Creep;
+use crate::tasks::task::TaskError::Invalid;
+use crate::tasks::task::{TaskContext, TaskOption, TaskResult};
+use crate::tasks::{TargetPolicy, TaskTrait};
+use screeps::ReturnCode;
+use serde::{Deserialize, Serialize};
+
+/// Tears down a structure, by default one of the hostile structures in the creep's room.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Dismantle {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    _target_id: Option<String>,
+    #[serde(default)]
+    policy: TargetPolicy,
+}
+
+impl Dismantle {
+    pub fn new(target_id: String) -> Dismantle {
+        Dismantle {
+            _target_id: Some(target_id),
+            ..Dismantle::default()
+        }
+    }
+
+    pub fn with_policy(policy: TargetPolicy) -> Dismantle {
+        Dismantle {
+            policy,
+            ..Dismantle::default()
+        }
+    }
+}
+
+impl TaskTrait for Dismantle {
+    fn name(&self) -> &str {
+        "Dismantle"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        if self._target_id.is_none() {
+            let pos = *creep.pos();
+            let candidates = context
+                .rooms
+                .get(creep.room_id())
+                .into_iter()
+                .flat_map(|room| room.hostile_structures())
+                .filter(|structure| structure.hits_max > 0)
+                .collect();
+            let _target = self.policy.select(
+                TargetPolicy::MostDamaged,
+                candidates,
+                |structure| pos.get_range_to(*structure),
+                |structure| structure.hits_max - structure.hits,
+            );
+            if let Some(target) = _target {
+                self._target_id = Some(target.id.clone());
+            }
+        }
+    }
+
+    fn execute(&mut self, creep: &Creep, _context: &mut TaskContext) -> TaskResult {
+        let target_id = &self._target_id.as_ref().ok_or_invalid()?;
+        // the target vanishing once it's torn down is what ends this task
+        let target = &api().get_structure(target_id).ok_or_invalid()?;
+        if creep.pos().is_near_to(target) {
+            match creep.dismantle(target_id) {
+                ReturnCode::Ok => {}
+                // no WORK parts, it's never going to get anywhere
+                ReturnCode::NoBodypart => return Err(Invalid),
+                r => warn!("couldn't dismantle: {:?}", r),
+            }
+        } else {
+            creep.move_to(target);
+        }
+        Ok(())
+    }
+}
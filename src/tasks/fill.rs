@@ -0,0 +1,245 @@
+use crate::api::{api, StructureState};
+use crate::data::{Creep, Reservations};
+use crate::tasks::task::TaskError::Invalid;
+use crate::tasks::task::{TaskContext, TaskResult};
+use crate::tasks::TaskTrait;
+use screeps::{Position, ReturnCode, StructureType};
+use serde::{Deserialize, Serialize};
+
+/// Order in which `Fill` visits the spawns and extensions of a room.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+pub enum FillOrder {
+    /// Always on to the closest one left, the shortest walk for one trip.
+    #[default]
+    Nearest,
+    /// Outwards from the first spawn, the same every time, so the room can be laid out
+    /// around the path fillers take.
+    Stable,
+}
+
+impl FillOrder {
+    fn is_nearest(&self) -> bool {
+        *self == FillOrder::Nearest
+    }
+}
+
+/// Tops up the spawns and extensions of the creep's room along a route planned in `start`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Fill {
+    #[serde(default, skip_serializing_if = "FillOrder::is_nearest")]
+    order: FillOrder,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    _route: Vec<String>,
+}
+
+/// Orders `targets` for a creep at `from`, leaving out those `energy` won't stretch to.
+fn plan_route(
+    from: Position,
+    mut targets: Vec<&StructureState>,
+    order: FillOrder,
+    mut energy: u32,
+) -> Vec<&StructureState> {
+    if order == FillOrder::Stable {
+        let anchor = targets
+            .iter()
+            .find(|target| target.structure_type == StructureType::Spawn)
+            .map_or(from, |spawn| spawn.pos);
+        targets.sort_by_key(|target| {
+            let (x, y) = target.pos.coords();
+            (anchor.get_range_to(&target.pos), y, x)
+        });
+    }
+
+    let mut route = vec![];
+    let mut pos = from;
+    while energy > 0 && !targets.is_empty() {
+        let index = match order {
+            FillOrder::Stable => 0,
+            FillOrder::Nearest => (0..targets.len())
+                .min_by_key(|index| pos.get_range_to(&targets[*index].pos))
+                .unwrap(),
+        };
+        let target = targets.remove(index);
+        energy = energy.saturating_sub(target.free_energy_capacity().unwrap_or(0));
+        pos = target.pos;
+        route.push(target);
+    }
+    route
+}
+
+impl Fill {
+    pub fn new(order: FillOrder) -> Fill {
+        Fill {
+            order,
+            _route: vec![],
+        }
+    }
+
+    /// Drops targets from the front of the route that are gone or already full, returning the
+    /// first one still missing energy.
+    fn next_target(&mut self) -> Option<StructureState> {
+        while let Some(target_id) = self._route.first() {
+            match api().get_structure(target_id) {
+                Some(target) if target.free_energy_capacity().unwrap_or(0) > 0 => {
+                    return Some(target)
+                }
+                _ => {
+                    self._route.remove(0);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl TaskTrait for Fill {
+    fn name(&self) -> &str {
+        "Fill"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        let reservations = &mut context.reservations;
+        if self._route.is_empty() {
+            let targets = context
+                .rooms
+                .get(creep.room_id())
+                .into_iter()
+                .flat_map(|room| {
+                    room.my_structures_of(&[StructureType::Spawn, StructureType::Extension])
+                })
+                .filter(|target| {
+                    let free = target.free_energy_capacity().unwrap_or(0);
+                    reservations.set_capacity(&target.id, free);
+                    reservations.remaining(&target.id).unwrap_or(0) > 0
+                })
+                .collect();
+            self._route = plan_route(*creep.pos(), targets, self.order, creep.energy())
+                .into_iter()
+                .map(|target| target.id.clone())
+                .collect();
+        }
+        self.reserve(creep, context.reservations);
+    }
+
+    fn execute(&mut self, creep: &Creep, _context: &mut TaskContext) -> TaskResult {
+        if creep.energy() == 0 {
+            return Err(Invalid);
+        }
+        let target = match self.next_target() {
+            Some(target) => target,
+            None => return Err(Invalid),
+        };
+        if !creep.pos().is_near_to(&target) {
+            creep.move_to(&target);
+            return Ok(());
+        }
+
+        let r = creep.transfer_all_energy(&target.id);
+        if r != ReturnCode::Ok {
+            warn!("couldn't fill {}: {:?}", target.id, r);
+            return Ok(());
+        }
+        self._route.remove(0);
+        if creep.energy() > target.free_energy_capacity().unwrap_or(0) {
+            if let Some(next) = self.next_target() {
+                creep.move_to(&next);
+            }
+        }
+        Ok(())
+    }
+
+    fn reserve(&self, creep: &Creep, reservations: &mut Reservations) {
+        let mut energy = creep.energy();
+        for target_id in self._route.iter() {
+            energy -= reservations.claim_available(target_id, creep.name(), energy);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{self, pos, TaskState};
+    use crate::api::{Intent, MockApi, Store};
+    use crate::tasks::run_creep_tasks;
+    use crate::travel::offset;
+
+    #[test]
+    fn fillers_walk_on_while_handing_energy_over() {
+        let mock = MockApi::install();
+        let ids: Vec<String> = {
+            let mut world = mock.world_mut();
+            world.add_spawn("Spawn1", pos(25, 24));
+            let creep = world.add_creep("Filler:1", pos(25, 25), 100);
+            creep.energy = 100;
+            creep.carry_total = 100;
+            [pos(27, 25), pos(30, 25), pos(20, 25)]
+                .iter()
+                .map(|pos| {
+                    let extension = world.add_structure(*pos, StructureType::Extension);
+                    extension.store = Some(Store {
+                        energy: 0,
+                        used: 0,
+                        capacity: 50,
+                    });
+                    extension.transferable = true;
+                    extension.id.clone()
+                })
+                .collect()
+        };
+        let mut state = TaskState::default();
+        let mut context = state.context();
+        let creep = fixtures::creep("Filler:1");
+        let closer = |creep: &Creep, target: Position| {
+            let step = *creep.pos() + offset(creep.next_move().unwrap());
+            step.get_range_to(&target) < creep.pos().get_range_to(&target)
+        };
+
+        // the spawn is full and 100 energy only go as far as the two extensions to the east
+        let mut fill = Fill::default();
+        fill.start(&creep, &mut context);
+        assert_eq!(fill._route, vec![ids[0].clone(), ids[1].clone()]);
+        assert!(fill.execute(&creep, &mut context).is_ok());
+        assert!(closer(&creep, pos(27, 25)));
+
+        mock.world_mut().creeps.get_mut("Filler:1").unwrap().pos = pos(26, 25);
+        let creep = fixtures::creep("Filler:1");
+        assert!(fill.execute(&creep, &mut context).is_ok());
+        assert_eq!(
+            mock.take_intents(),
+            vec![Intent::Transfer {
+                creep: "Filler:1".to_string(),
+                target: ids[0].clone(),
+            }]
+        );
+        // handing over to the first extension, the creep is already on its way to the second
+        assert_eq!(fill._route, vec![ids[1].clone()]);
+        assert!(closer(&creep, pos(30, 25)));
+
+        // what was filled is dropped from the route in memory too, a reset doesn't walk back
+        context.reservations.clear();
+        let mut creep = fixtures::creep("Filler:1");
+        creep.push_task(Fill::default().into());
+        run_creep_tasks(&mut creep, &|_, _| None, &mut context);
+        creep.flush_tasks();
+        let restored = fixtures::creep("Filler:1");
+        let rest = Fill {
+            _route: vec![ids[1].clone()],
+            ..Fill::default()
+        };
+        assert_eq!(restored.tasks(), &[rest.into()]);
+
+        // and once the second one is filled the route is empty
+        mock.world_mut().creeps.get_mut("Filler:1").unwrap().pos = pos(29, 25);
+        creep.refresh(api().creeps().remove(0));
+        run_creep_tasks(&mut creep, &|_, _| None, &mut context);
+        creep.flush_tasks();
+        let restored = fixtures::creep("Filler:1");
+        assert_eq!(restored.tasks(), &[Fill::default().into()]);
+
+        let stable = Fill::new(FillOrder::Stable);
+        let packed = serde_json::to_string(&stable).unwrap();
+        assert_eq!(serde_json::from_str::<Fill>(&packed).unwrap(), stable);
+        assert_eq!(serde_json::to_string(&Fill::default()).unwrap(), "{}");
+    }
+}
@@ -1,19 +1,23 @@
+use crate::api::{api, SourceState};
 use crate::data::Creep;
 use crate::tasks::task::TaskError::Invalid;
-use crate::tasks::task::{TaskOption, TaskResult};
+use crate::tasks::task::{TaskContext, TaskOption, TaskResult};
 use crate::tasks::TaskTrait;
-use screeps::{find, HasId, ReturnCode, Source};
+use screeps::{Position, ReturnCode};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
 pub struct Harvest {
     #[serde(skip_serializing_if = "Option::is_none")]
     _source_id: Option<String>,
+    // handed out again by the source in `start`, so there's no point in persisting it
+    #[serde(skip)]
+    _slot: Option<Position>,
 }
 
 impl Harvest {
-    fn is_valid(&self, creep: &Creep, source: &Source) -> TaskResult {
-        if source.energy() == 0 && source.ticks_to_regeneration() > 10
+    fn is_valid(&self, creep: &Creep, source: &SourceState) -> TaskResult {
+        if source.energy == 0 && source.ticks_to_regeneration > 10
             || creep.carry_total() == creep.carry_capacity()
         {
             return Err(Invalid);
@@ -27,35 +31,91 @@ impl TaskTrait for Harvest {
         "Harvest"
     }
 
-    fn start(&mut self, creep: &Creep) {
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
         if self._source_id.is_none() {
-            let _source = creep
-                .room()
-                .find(find::SOURCES)
-                .iter()
-                .find(|source| source.energy() > 0)
-                .cloned();
+            let _source = context
+                .rooms
+                .get(creep.room_id())
+                .into_iter()
+                .flat_map(|room| room.sources())
+                .filter(|source| source.energy() > 0)
+                .find(|source| source.free_slots() > 0);
             if let Some(source) = _source {
-                self._source_id = Some(source.id());
+                self._source_id = Some(source.id().to_string());
             }
         }
+        if let Some(source_id) = &self._source_id {
+            // after a global reset this re-claims the slot we were already standing on
+            self._slot = context
+                .source_mut(source_id)
+                .and_then(|source| source.assign(creep.name(), *creep.pos()));
+        }
     }
 
-    fn execute(&self, creep: &Creep) -> TaskResult {
+    fn execute(&mut self, creep: &Creep, _context: &mut TaskContext) -> TaskResult {
         let source_id = &self._source_id.as_ref().ok_or_invalid()?;
-        let source = &screeps::game::get_object_typed(source_id)?.ok_or_invalid()?;
+        let source = &api().get_source(source_id).ok_or_invalid()?;
         self.is_valid(creep, source)?;
-        if creep.pos().is_near_to(source) {
-            let r = creep.harvest(source);
+        let in_place = match self._slot {
+            Some(slot) => *creep.pos() == slot,
+            None => creep.pos().is_near_to(source),
+        };
+        if in_place {
+            let r = creep.harvest(source_id);
             if r != ReturnCode::Ok {
                 // this will invalidate the task next tick
                 if r != ReturnCode::NotEnough {
                     warn!("couldn't harvest upgrader: {:?}", r);
                 }
             }
+        } else if let Some(slot) = self._slot {
+            creep.move_to(&slot);
         } else {
             creep.move_to(source);
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{creep, pos, TaskState};
+    use crate::api::MockApi;
+    use screeps::{Direction, Terrain};
+
+    #[test]
+    fn creeps_only_take_free_slots_next_to_a_source() {
+        let mock = MockApi::install();
+        let source_id = {
+            let mut world = mock.world_mut();
+            // walls everywhere around the source except for (11, 10)
+            for x in 9..=11 {
+                for y in 9..=11 {
+                    world.terrain.insert(pos(x, y), Terrain::Wall);
+                }
+            }
+            world.terrain.remove(&pos(11, 10));
+            world.add_creep("Harvester:1", pos(12, 10), 50);
+            world.add_creep("Harvester:2", pos(20, 20), 50);
+            world.add_source(pos(10, 10)).id.clone()
+        };
+        let first = creep("Harvester:1");
+        let second = creep("Harvester:2");
+        let mut state = TaskState::default();
+        let mut context = state.context();
+
+        let mut harvest = Harvest::default();
+        harvest.start(&first, &mut context);
+        assert_eq!(harvest._source_id.as_ref(), Some(&source_id));
+        assert!(harvest.execute(&first, &mut context).is_ok());
+
+        let mut crowded = Harvest::default();
+        crowded.start(&second, &mut context);
+        assert!(crowded.execute(&second, &mut context).is_err());
+
+        assert_eq!(first.next_move(), Some(Direction::Left));
+        assert_eq!(second.next_move(), None);
+        assert!(mock.take_intents().is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::api::api;
+use crate::data::{Creep, Room, Source};
+use crate::tasks::task::{TaskContext, TaskOption, TaskResult};
+use crate::tasks::TaskTrait;
+use screeps::{Position, ReturnCode, StructureType};
+use serde::{Deserialize, Serialize};
+
+/// Parks next to a source for good and harvests it, handing energy to its container or link.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Mine {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    _source_id: Option<String>,
+    // handed out again by the source in `start`, so there's no point in persisting it
+    #[serde(skip)]
+    _slot: Option<Position>,
+}
+
+/// The slot of `source` to mine from: the one with a container, planned or built, or else
+/// one next to one of our links.
+fn drop_off_tile(room: &Room, source: &Source) -> Option<Position> {
+    let slots = source.slots();
+    let sites = room
+        .construction_sites()
+        .iter()
+        .filter(|site| site.my && site.structure_type == StructureType::Container)
+        .map(|site| site.pos);
+    room.structures_of(StructureType::Container)
+        .map(|container| container.pos)
+        .chain(sites)
+        .find(|pos| slots.contains(pos))
+        .or_else(|| {
+            slots.iter().cloned().find(|slot| {
+                room.my_structures_of(&[StructureType::Link])
+                    .any(|link| slot.is_near_to(link))
+            })
+        })
+}
+
+impl Mine {
+    pub fn new(source_id: String) -> Mine {
+        Mine {
+            _source_id: Some(source_id),
+            _slot: None,
+        }
+    }
+}
+
+impl TaskTrait for Mine {
+    fn name(&self) -> &str {
+        "Mine"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        if self._source_id.is_none() {
+            let _source = context
+                .rooms
+                .get(creep.room_id())
+                .into_iter()
+                .flat_map(|room| room.sources())
+                .find(|source| source.miner().is_none_or(|miner| miner == creep.name()));
+            if let Some(source) = _source {
+                self._source_id = Some(source.id().to_string());
+            }
+        }
+        if let Some(source_id) = &self._source_id {
+            let drop_off = context.rooms.values().find_map(|room| {
+                let source = room.sources().iter().find(|s| s.id() == source_id)?;
+                drop_off_tile(room, source)
+            });
+            match context.source_mut(source_id) {
+                Some(source) if source.miner().is_none_or(|miner| miner == creep.name()) => {
+                    source.set_miner(creep.name());
+                    self._slot = source.assign(creep.name(), drop_off.unwrap_or(*creep.pos()));
+                }
+                // someone else mines it, without a slot the task ends right away
+                Some(source) => debug!("{} already mined by {:?}", source_id, source.miner()),
+                None => {}
+            }
+        }
+    }
+
+    fn execute(&mut self, creep: &Creep, context: &mut TaskContext) -> TaskResult {
+        let source_id = &self._source_id.as_ref().ok_or_invalid()?;
+        let source = &api().get_source(source_id).ok_or_invalid()?;
+        let slot = self._slot.ok_or_invalid()?;
+        if *creep.pos() != slot {
+            creep.move_to(&slot);
+            return Ok(());
+        }
+        // whoever passes by has to go around, the container under us is where the energy goes
+        creep.pin();
+
+        let room = context.rooms.get(&slot.room_name()).ok_or_invalid()?;
+        let link = room
+            .my_structures_of(&[StructureType::Link])
+            .filter(|link| link.free_energy_capacity().unwrap_or(0) > 0)
+            .find(|link| slot.is_near_to(*link));
+        let full = creep.energy() > 0 && creep.carry_total() == creep.carry_capacity();
+        if let Some(link) = link.filter(|_| creep.energy() > 0) {
+            let r = creep.transfer_all_energy(&link.id);
+            if r != ReturnCode::Ok {
+                warn!("couldn't transfer energy into link: {:?}", r);
+            }
+        } else if full {
+            let container = room
+                .structures_of(StructureType::Container)
+                .find(|container| container.pos == slot && container.hits < container.hits_max);
+            if let Some(container) = container {
+                creep.repair(&container.id);
+                return Ok(());
+            }
+            let site = room.construction_sites().iter().find(|site| {
+                site.my && site.pos == slot && site.structure_type == StructureType::Container
+            });
+            if let Some(site) = site {
+                creep.build(&site.id);
+                return Ok(());
+            }
+        }
+
+        if source.energy > 0 {
+            let r = creep.harvest(source_id);
+            if r != ReturnCode::Ok {
+                warn!("couldn't harvest: {:?}", r);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{self, pos, room_name, TaskState};
+    use crate::api::{Intent, MockApi, Store};
+    use crate::data::Role;
+    use crate::roles::Miner;
+    use crate::travel::with_matrices;
+    use screeps::Direction;
+
+    fn store(energy: u32, capacity: u32) -> Option<Store> {
+        Some(Store {
+            energy,
+            used: energy,
+            capacity,
+        })
+    }
+
+    #[test]
+    fn miners_park_on_the_container_and_feed_the_link() {
+        let mock = MockApi::install();
+        let (source_id, container_id, link_id) = {
+            let mut world = mock.world_mut();
+            let source_id = world.add_source(pos(10, 10)).id.clone();
+            let container = world.add_structure(pos(11, 11), StructureType::Container);
+            container.hits = 100;
+            container.hits_max = 250_000;
+            container.store = store(0, 2000);
+            let container_id = container.id.clone();
+            let link = world.add_structure(pos(12, 12), StructureType::Link);
+            link.store = store(0, 800);
+            link.transferable = true;
+            let link_id = link.id.clone();
+            world.add_creep("Miner:1", pos(20, 20), 50);
+            (source_id, container_id, link_id)
+        };
+        let mut state = TaskState::default();
+        with_matrices(|matrices| matrices.refresh(&state.rooms));
+        let mut context = state.context();
+        let creep = fixtures::creep("Miner:1");
+
+        let mut mine = Mine::default();
+        mine.start(&creep, &mut context);
+        assert_eq!(mine._slot, Some(pos(11, 11)));
+        assert!(mine.execute(&creep, &mut context).is_ok());
+        // the link is in the way of the straight line there
+        assert_eq!(creep.next_move(), Some(Direction::Top));
+
+        {
+            let mut world = mock.world_mut();
+            let state = world.creeps.get_mut("Miner:1").unwrap();
+            state.pos = pos(11, 11);
+            state.energy = 50;
+            state.carry_total = 50;
+        }
+        let creep = fixtures::creep("Miner:1");
+        assert!(mine.execute(&creep, &mut context).is_ok());
+        assert!(creep.is_pinned());
+
+        // the link is gone, so the full load goes into the container's hits instead
+        mock.world_mut().structures.remove(&link_id);
+        context.rooms.get_mut(&room_name()).unwrap().refresh();
+        assert!(mine.execute(&creep, &mut context).is_ok());
+
+        assert_eq!(
+            mock.take_intents(),
+            vec![
+                Intent::Transfer {
+                    creep: "Miner:1".to_string(),
+                    target: link_id,
+                },
+                Intent::Harvest {
+                    creep: "Miner:1".to_string(),
+                    target: source_id,
+                },
+                Intent::Repair {
+                    creep: "Miner:1".to_string(),
+                    target: container_id,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sources_keep_their_miner() {
+        let mock = MockApi::install();
+        let source_id = {
+            let mut world = mock.world_mut();
+            world.add_creep("Miner:1", pos(20, 20), 50);
+            world.add_creep("Miner:2", pos(21, 20), 50);
+            world.add_source(pos(10, 10)).id.clone()
+        };
+        let mut state = TaskState::default();
+        let mut context = state.context();
+        let first = fixtures::creep("Miner:1");
+        let second = fixtures::creep("Miner:2");
+
+        Mine::default().start(&first, &mut context);
+        assert_eq!(
+            Miner.next_task(&first, context.rooms),
+            Some(Mine::new(source_id.clone()).into())
+        );
+        assert_eq!(Miner.next_task(&second, context.rooms), None);
+
+        // asked for by name, the source still isn't taken from the miner it has
+        let mut mine = Mine::new(source_id.clone());
+        mine.start(&second, &mut context);
+        assert!(mine.execute(&second, &mut context).is_err());
+        let source = context.source_mut(&source_id).unwrap();
+        assert_eq!(source.miner(), Some("Miner:1"));
+    }
+}
@@ -1,6 +1,33 @@
+mod build;
+mod composite;
+mod dismantle;
+mod fill;
 mod harvest;
+mod mine;
+mod pickup;
+mod policy;
+mod repair;
+mod runner;
 mod task;
+mod transfer;
+mod upgrade;
+mod withdraw;
 
+pub use build::Build;
+pub use composite::{Fallback, Repeat, Sequence, Until};
+pub use dismantle::Dismantle;
+pub use fill::{Fill, FillOrder};
 pub use harvest::Harvest;
+pub use mine::Mine;
+pub use pickup::{Pickup, PileKind};
+pub use policy::TargetPolicy;
+pub use repair::Repair;
+pub use runner::run_creep_tasks;
+pub use runner::IdleHook;
 pub use task::Task;
+pub use task::TaskContext;
+pub use task::TaskError;
 pub use task::TaskTrait;
+pub use transfer::Transfer;
+pub use upgrade::Upgrade;
+pub use withdraw::Withdraw;
@@ -0,0 +1,108 @@
+use crate::api::api;
+use crate::data::{Creep, Reservations};
+use crate::tasks::task::TaskError::Invalid;
+use crate::tasks::task::{TaskContext, TaskOption, TaskResult};
+use crate::tasks::{TargetPolicy, TaskTrait};
+use screeps::ReturnCode;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum PileKind {
+    Dropped,
+    Tombstone,
+    Ruin,
+}
+
+/// Collects energy lying around: dropped resources, tombstones and ruins.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Pickup {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    _target_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    _kind: Option<PileKind>,
+    #[serde(default)]
+    policy: TargetPolicy,
+}
+
+impl Pickup {
+    pub fn new(target_id: String, kind: PileKind) -> Pickup {
+        Pickup {
+            _target_id: Some(target_id),
+            _kind: Some(kind),
+            ..Pickup::default()
+        }
+    }
+
+    pub fn with_policy(policy: TargetPolicy) -> Pickup {
+        Pickup {
+            policy,
+            ..Pickup::default()
+        }
+    }
+
+    fn is_valid(&self, creep: &Creep, amount: u32) -> TaskResult {
+        if creep.carry_total() == creep.carry_capacity() || amount == 0 {
+            return Err(Invalid);
+        }
+        Ok(())
+    }
+}
+
+impl TaskTrait for Pickup {
+    fn name(&self) -> &str {
+        "Pickup"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        if self._target_id.is_none() {
+            let pos = *creep.pos();
+            let room = context.rooms.get(creep.room_id());
+            let reservations = &mut context.reservations;
+            let piles = room
+                .into_iter()
+                .flat_map(|room| room.piles())
+                .filter(|pile| {
+                    reservations.set_capacity(&pile.id, pile.amount);
+                    reservations.remaining(&pile.id).unwrap_or(0) > 0
+                })
+                .collect();
+            let _pile = self.policy.select(
+                TargetPolicy::LargestPile,
+                piles,
+                |pile| pos.get_range_to(&pile.pos),
+                |pile| pile.amount,
+            );
+            if let Some(pile) = _pile {
+                self._target_id = Some(pile.id.clone());
+                self._kind = Some(pile.kind);
+            }
+        }
+        self.reserve(creep, context.reservations);
+    }
+
+    fn execute(&mut self, creep: &Creep, _context: &mut TaskContext) -> TaskResult {
+        let target_id = self._target_id.as_ref().ok_or_invalid()?;
+        let kind = self._kind.ok_or_invalid()?;
+        let pile = api().get_pile(target_id, kind).ok_or_invalid()?;
+        self.is_valid(creep, pile.amount)?;
+        if !creep.pos().is_near_to(&pile) {
+            creep.move_to(&pile);
+            return Ok(());
+        }
+        let r = match kind {
+            PileKind::Dropped => creep.pickup(target_id),
+            PileKind::Tombstone | PileKind::Ruin => creep.withdraw_all_energy(target_id),
+        };
+        if r != ReturnCode::Ok {
+            warn!("couldn't pick up energy: {:?}", r);
+        }
+        Ok(())
+    }
+
+    fn reserve(&self, creep: &Creep, reservations: &mut Reservations) {
+        if let Some(target_id) = &self._target_id {
+            let free = creep.carry_capacity() - creep.carry_total();
+            reservations.claim_available(target_id, creep.name(), free);
+        }
+    }
+}
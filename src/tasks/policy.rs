@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+/// How a task settles on a target in `start` when it wasn't given one.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+pub enum TargetPolicy {
+    #[default]
+    Closest,
+    MostDamaged,
+    LargestPile,
+}
+
+impl TargetPolicy {
+    /// Picks one of `candidates` by `weight` if this is the `supported` policy, or else by
+    /// `range`.
+    pub fn select<T, R, W>(
+        self,
+        supported: TargetPolicy,
+        candidates: Vec<T>,
+        range: R,
+        weight: W,
+    ) -> Option<T>
+    where
+        R: Fn(&T) -> u32,
+        W: Fn(&T) -> u32,
+    {
+        if self == TargetPolicy::Closest || self != supported {
+            return candidates.into_iter().min_by_key(|c| range(c));
+        }
+        candidates
+            .into_iter()
+            .min_by_key(|c| (std::cmp::Reverse(weight(c)), range(c)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (range, weight)
+    fn candidates() -> Vec<(u32, u32)> {
+        vec![(5, 100), (2, 10), (9, 400), (7, 400)]
+    }
+
+    #[test]
+    fn closest_ignores_weight() {
+        let pile = TargetPolicy::LargestPile;
+        let picked = TargetPolicy::Closest.select(pile, candidates(), |c| c.0, |c| c.1);
+        assert_eq!(picked, Some((2, 10)));
+        // a task that only knows about damage has no idea what makes a pile large
+        let damage = TargetPolicy::MostDamaged;
+        let picked = TargetPolicy::LargestPile.select(damage, candidates(), |c| c.0, |c| c.1);
+        assert_eq!(picked, Some((2, 10)));
+    }
+
+    #[test]
+    fn heaviest_wins_and_range_breaks_ties() {
+        let pile = TargetPolicy::LargestPile;
+        let picked = TargetPolicy::LargestPile.select(pile, candidates(), |c| c.0, |c| c.1);
+        assert_eq!(picked, Some((7, 400)));
+        let damage = TargetPolicy::MostDamaged;
+        let picked =
+            TargetPolicy::MostDamaged.select(damage, vec![], |c: &(u32, u32)| c.0, |c| c.1);
+        assert_eq!(picked, None);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::api::{api, Owner, StructureState};
+use crate::data::Creep;
+use crate::tasks::task::TaskError::Invalid;
+use crate::tasks::task::{TaskContext, TaskOption, TaskResult};
+use crate::tasks::{TargetPolicy, TaskTrait};
+use screeps::ReturnCode;
+use serde::{Deserialize, Serialize};
+
+/// Repairs a structure until its hits reach `until` of its maximum.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Repair {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    _target_id: Option<String>,
+    #[serde(default)]
+    policy: TargetPolicy,
+    #[serde(default = "full_hits")]
+    until: f32,
+}
+
+fn full_hits() -> f32 {
+    1.0
+}
+
+impl Default for Repair {
+    fn default() -> Repair {
+        Repair {
+            _target_id: None,
+            policy: TargetPolicy::default(),
+            until: full_hits(),
+        }
+    }
+}
+
+/// `(hits, hits_max)` of `structure`, `None` for structures without hits or owned by others.
+fn hits(structure: &StructureState) -> Option<(u32, u32)> {
+    if structure.owner == Owner::Hostile {
+        return None;
+    }
+    match structure.hits_max {
+        0 => None,
+        hits_max => Some((structure.hits, hits_max)),
+    }
+}
+
+impl Repair {
+    pub fn new(target_id: String) -> Repair {
+        Repair {
+            _target_id: Some(target_id),
+            ..Repair::default()
+        }
+    }
+
+    pub fn with_policy(policy: TargetPolicy) -> Repair {
+        Repair {
+            policy,
+            ..Repair::default()
+        }
+    }
+
+    /// Ends the task once the structure reaches `fraction` of its maximum hits, and only
+    /// considers structures below that when picking a target.
+    pub fn until(mut self, fraction: f32) -> Repair {
+        self.until = fraction;
+        self
+    }
+
+    fn needs_repair(&self, structure: &StructureState) -> bool {
+        match hits(structure) {
+            Some((hits, hits_max)) => (hits as f32) < hits_max as f32 * self.until,
+            None => false,
+        }
+    }
+
+    fn is_valid(&self, creep: &Creep, target: &StructureState) -> TaskResult {
+        if creep.energy() == 0 || !self.needs_repair(target) {
+            return Err(Invalid);
+        }
+        Ok(())
+    }
+}
+
+impl TaskTrait for Repair {
+    fn name(&self) -> &str {
+        "Repair"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        if self._target_id.is_none() {
+            let pos = *creep.pos();
+            let candidates = context
+                .rooms
+                .get(creep.room_id())
+                .into_iter()
+                .flat_map(|room| room.structures())
+                .filter(|structure| self.needs_repair(structure))
+                .collect();
+            let _target = self.policy.select(
+                TargetPolicy::MostDamaged,
+                candidates,
+                |structure| pos.get_range_to(*structure),
+                |structure| hits(structure).map_or(0, |(hits, max)| max - hits),
+            );
+            if let Some(target) = _target {
+                self._target_id = Some(target.id.clone());
+            }
+        }
+    }
+
+    fn execute(&mut self, creep: &Creep, _context: &mut TaskContext) -> TaskResult {
+        let target_id = &self._target_id.as_ref().ok_or_invalid()?;
+        let target = &api().get_structure(target_id).ok_or_invalid()?;
+        self.is_valid(creep, target)?;
+        if creep.pos().in_range_to(target, 3) {
+            let r = creep.repair(target_id);
+            if r != ReturnCode::Ok {
+                warn!("couldn't repair: {:?}", r);
+            }
+        } else {
+            creep.move_to(target);
+        }
+        Ok(())
+    }
+}
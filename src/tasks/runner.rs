@@ -0,0 +1,146 @@
+use crate::data::{Creep, Room};
+use crate::logging::{current_context, with_context};
+use crate::tasks::task::TaskError;
+use crate::tasks::{Task, TaskContext, TaskTrait};
+use screeps::RoomName;
+use std::collections::HashMap;
+
+/// How many tasks a creep may step through in a single tick. Finished tasks only notice that
+/// they are done when executed, so we allow a few of them to fall through to the next one.
+const MAX_TASKS_PER_TICK: usize = 4;
+/// How many ticks in a row a task may fail with `TaskError::Error` before we give up on it.
+const MAX_TASK_FAILURES: u32 = 5;
+
+/// Called for creeps with an empty task queue, returning the task they should work on next.
+pub type IdleHook = dyn Fn(&Creep) -> Option<Task> + Send;
+
+/// Hands out the next task to creeps with an empty task queue, given the rooms we can see.
+pub type NextTask<'a> = dyn Fn(&Creep, &HashMap<RoomName, Room>) -> Option<Task> + 'a;
+
+/// Drives the task queue of a single creep for the current tick, asking `idle` for work.
+pub fn run_creep_tasks(creep: &mut Creep, idle: &NextTask<'_>, context: &mut TaskContext) {
+    if creep.spawning() {
+        return;
+    }
+
+    let mut tasks = creep.take_tasks();
+    for _ in 0..MAX_TASKS_PER_TICK {
+        if tasks.is_empty() {
+            match idle(creep, context.rooms) {
+                Some(task) => {
+                    debug!("{} is idle, picking up {}", creep.name(), task.name());
+                    tasks.push(task);
+                    creep.mark_tasks_dirty();
+                }
+                None => break,
+            }
+        }
+
+        let task = &mut tasks[0];
+        let log_context = current_context().unwrap_or_default().with_task(task.name());
+        let result = with_context(log_context, || {
+            if !creep.task_started() {
+                task.start(creep, context);
+                creep.set_task_started(true);
+            }
+            task.execute(creep, context)
+        });
+        // start settles on targets and execute works through them, which has to survive a
+        // reset; flushing only writes the queue if its packed form changed
+        creep.mark_tasks_dirty();
+        match result {
+            Ok(()) => {
+                // harvesting, building and the like happen in range of the target
+                if creep.next_move().is_none() {
+                    creep.pin();
+                }
+                creep.set_task_failures(0);
+                break;
+            }
+            Err(TaskError::Invalid) | Err(TaskError::OptionFailed) => {
+                debug!("{} finished {}", creep.name(), task.name());
+            }
+            Err(TaskError::ConversionError(error)) => {
+                warn!(
+                    "{} dropping {}, target has the wrong type: {:?}",
+                    creep.name(),
+                    task.name(),
+                    error
+                );
+            }
+            Err(TaskError::Error(error)) => {
+                let failures = creep.task_failures() + 1;
+                if failures < MAX_TASK_FAILURES {
+                    warn!(
+                        "{} failed {} ({} of {}): {}",
+                        creep.name(),
+                        task.name(),
+                        failures,
+                        MAX_TASK_FAILURES,
+                        error
+                    );
+                    creep.set_task_failures(failures);
+                    break;
+                }
+                error!(
+                    "{} dropping {} after {} failures: {}",
+                    creep.name(),
+                    task.name(),
+                    failures,
+                    error
+                );
+            }
+        }
+
+        let task = tasks.remove(0);
+        context.release_task(creep, &task);
+        creep.mark_tasks_dirty();
+        creep.set_task_started(false);
+        creep.set_task_failures(0);
+    }
+    creep.set_tasks(tasks);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{self, pos, TaskState};
+    use crate::api::{Intent, MockApi};
+    use crate::tasks::{Build, Upgrade};
+    use screeps::StructureType;
+
+    #[test]
+    fn finished_tasks_fall_through_and_the_queue_survives_a_reset() {
+        let mock = MockApi::install();
+        let site_id = {
+            let mut world = mock.world_mut();
+            world.add_controller(pos(40, 40));
+            let creep = world.add_creep("Builder:1", pos(10, 10), 50);
+            creep.energy = 50;
+            creep.carry_total = 50;
+            let site = world.add_construction_site(pos(11, 11), StructureType::Road, 300);
+            site.id.clone()
+        };
+        let mut creep = fixtures::creep("Builder:1");
+        creep.push_task(Build::new("gone".to_string()).into());
+        creep.push_task(Build::new(site_id.clone()).into());
+        creep.push_task(Upgrade::default().into());
+        let mut state = TaskState::default();
+        let mut context = state.context();
+
+        run_creep_tasks(&mut creep, &|_, _| None, &mut context);
+        assert_eq!(creep.tasks().len(), 2);
+        assert!(creep.is_pinned());
+        assert_eq!(
+            mock.take_intents(),
+            vec![Intent::Build {
+                creep: "Builder:1".to_string(),
+                target: site_id,
+            }]
+        );
+
+        creep.flush_tasks();
+        let restored = fixtures::creep("Builder:1");
+        assert_eq!(restored.tasks(), creep.tasks());
+    }
+}
@@ -2,11 +2,16 @@ use enum_dispatch::enum_dispatch;
 
 use serde::{Deserialize, Serialize};
 
-use crate::data::Creep;
-use crate::tasks::Harvest;
-use screeps::ConversionError;
+use crate::data::{Creep, Reservations, Room, Source};
+use crate::tasks::{
+    Build, Dismantle, Fallback, Fill, Harvest, Mine, Pickup, Repair, Repeat, Sequence, Transfer,
+    Upgrade, Withdraw,
+};
+use screeps::{ConversionError, RoomName};
+use std::collections::HashMap;
 use std::error::Error;
 
+#[derive(Debug)]
 pub enum TaskError {
     Invalid,
     OptionFailed,
@@ -35,15 +40,97 @@ impl From<ConversionError> for TaskError {
 
 pub type TaskResult = Result<(), TaskError>;
 
+/// Shared state tasks get to look at and update while they run.
+pub struct TaskContext<'a> {
+    pub reservations: &'a mut Reservations,
+    pub rooms: &'a mut HashMap<RoomName, Room>,
+}
+
+impl<'a> TaskContext<'a> {
+    /// Looks `id` up in every room we can see, tasks may target sources outside their room.
+    pub fn source_mut(&mut self, id: &str) -> Option<&mut Source> {
+        self.rooms.values_mut().find_map(|room| room.source_mut(id))
+    }
+
+    /// Drops what `task` of `creep` claimed or was assigned, used when it ends. Claims of the
+    /// tasks queued after it stay.
+    pub fn release_task(&mut self, creep: &Creep, task: &Task) {
+        let mut claims = Reservations::new();
+        task.reserve(creep, &mut claims);
+        for target in claims.targets() {
+            self.reservations.release(target, creep.name());
+        }
+        // only the task at the head of the queue holds a slot
+        for room in self.rooms.values_mut() {
+            room.release_creep(creep.name());
+        }
+    }
+}
+
 #[enum_dispatch]
 pub trait TaskTrait {
     fn name(&self) -> &str;
-    fn start(&mut self, creep: &Creep);
-    fn execute(&self, creep: &Creep) -> TaskResult;
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext);
+    fn execute(&mut self, creep: &Creep, context: &mut TaskContext) -> TaskResult;
+
+    /// Claims what the task is going to take or deliver. Claims only last a tick, so this
+    /// runs for every queued task at the start of each one.
+    fn reserve(&self, _creep: &Creep, _reservations: &mut Reservations) {}
 }
 
 #[enum_dispatch(TaskTrait)]
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum Task {
     Harvest,
+    Build,
+    Upgrade,
+    Transfer,
+    Withdraw,
+    Repair,
+    Dismantle,
+    Pickup,
+    Mine,
+    Fill,
+    Sequence,
+    Repeat,
+    Fallback,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::{PileKind, TargetPolicy, Until};
+
+    #[test]
+    fn tasks_survive_a_round_trip_through_memory() {
+        let tasks: Vec<Task> = vec![
+            Harvest::default().into(),
+            Build::new("site".to_string()).into(),
+            Upgrade::default().into(),
+            Transfer::new("spawn".to_string()).into(),
+            Withdraw::new("container".to_string()).into(),
+            Repair::with_policy(TargetPolicy::MostDamaged)
+                .until(0.5)
+                .into(),
+            Dismantle::new("wall".to_string()).into(),
+            Pickup::new("tombstone".to_string(), PileKind::Tombstone).into(),
+            Repeat::new(
+                Sequence::new(vec![
+                    Harvest::default().into(),
+                    Fallback::new(vec![Transfer::default().into(), Upgrade::default().into()])
+                        .into(),
+                ])
+                .into(),
+            )
+            .until(Until::Times(3))
+            .into(),
+        ];
+        let packed = serde_json::to_string(&tasks).unwrap();
+        let unpacked: Vec<Task> = serde_json::from_str(&packed).unwrap();
+        assert_eq!(tasks, unpacked);
+
+        // fields left out of hand-written tasks take their defaults
+        let written: Task = serde_json::from_str(r#"{"Repair":{}}"#).unwrap();
+        assert_eq!(written, Repair::default().into());
+    }
 }
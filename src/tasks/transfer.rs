@@ -0,0 +1,98 @@
+use crate::api::{api, StructureState};
+use crate::data::{Creep, Reservations};
+use crate::tasks::task::TaskError::Invalid;
+use crate::tasks::task::{TaskContext, TaskOption, TaskResult};
+use crate::tasks::TaskTrait;
+use screeps::{ReturnCode, StructureType};
+use serde::{Deserialize, Serialize};
+
+/// Delivers energy, by default into the closest spawn, extension or tower that isn't full.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Transfer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    _target_id: Option<String>,
+    // what a hauler sent to pick it up first is bringing, see `Logistics::assign`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    _amount: Option<u32>,
+}
+
+impl Transfer {
+    pub fn new(target_id: String) -> Transfer {
+        Transfer {
+            _target_id: Some(target_id),
+            _amount: None,
+        }
+    }
+
+    /// Sets the energy the creep is going to bring, for it to claim before it has any.
+    pub fn amount(mut self, amount: u32) -> Transfer {
+        self._amount = Some(amount);
+        self
+    }
+
+    fn is_valid(&self, creep: &Creep, target: &StructureState) -> TaskResult {
+        if creep.energy() == 0 || target.free_energy_capacity().unwrap_or(0) == 0 {
+            return Err(Invalid);
+        }
+        Ok(())
+    }
+}
+
+impl TaskTrait for Transfer {
+    fn name(&self) -> &str {
+        "Transfer"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        if self._target_id.is_none() {
+            let pos = creep.pos();
+            let room = context.rooms.get(creep.room_id());
+            let reservations = &mut context.reservations;
+            let _target = room
+                .into_iter()
+                .flat_map(|room| {
+                    room.my_structures_of(&[
+                        StructureType::Spawn,
+                        StructureType::Extension,
+                        StructureType::Tower,
+                    ])
+                })
+                .filter(|structure| {
+                    let free = structure.free_energy_capacity().unwrap_or(0);
+                    reservations.set_capacity(&structure.id, free);
+                    reservations.remaining(&structure.id).unwrap_or(0) > 0
+                })
+                .min_by_key(|structure| pos.get_range_to(*structure));
+            if let Some(target) = _target {
+                self._target_id = Some(target.id.clone());
+            }
+        }
+        self.reserve(creep, context.reservations);
+    }
+
+    fn execute(&mut self, creep: &Creep, _context: &mut TaskContext) -> TaskResult {
+        let target_id = &self._target_id.as_ref().ok_or_invalid()?;
+        let target = &api().get_structure(target_id).ok_or_invalid()?;
+        self.is_valid(creep, target)?;
+        if creep.pos().is_near_to(target) {
+            let r = creep.transfer_all_energy(target_id);
+            if r != ReturnCode::Ok {
+                warn!("couldn't transfer energy: {:?}", r);
+            }
+        } else {
+            creep.move_to(target);
+        }
+        Ok(())
+    }
+
+    /// Claims what the creep carries, or what it was sent to pick up first if it's empty.
+    fn reserve(&self, creep: &Creep, reservations: &mut Reservations) {
+        if let Some(target_id) = &self._target_id {
+            let amount = match creep.energy() {
+                0 => self._amount.unwrap_or(0),
+                energy => energy,
+            };
+            reservations.claim_available(target_id, creep.name(), amount);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::api::{api, ControllerState};
+use crate::data::Creep;
+use crate::tasks::task::TaskError::Invalid;
+use crate::tasks::task::{TaskContext, TaskOption, TaskResult};
+use crate::tasks::TaskTrait;
+use screeps::ReturnCode;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Upgrade {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    _controller_id: Option<String>,
+}
+
+impl Upgrade {
+    fn is_valid(&self, creep: &Creep, controller: &ControllerState) -> TaskResult {
+        if creep.energy() == 0 || !controller.my {
+            return Err(Invalid);
+        }
+        Ok(())
+    }
+}
+
+impl TaskTrait for Upgrade {
+    fn name(&self) -> &str {
+        "Upgrade"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        if self._controller_id.is_none() {
+            if let Some(controller) = context
+                .rooms
+                .get(creep.room_id())
+                .and_then(|room| room.controller())
+            {
+                self._controller_id = Some(controller.id.clone());
+            }
+        }
+    }
+
+    fn execute(&mut self, creep: &Creep, _context: &mut TaskContext) -> TaskResult {
+        let controller_id = &self._controller_id.as_ref().ok_or_invalid()?;
+        let controller = &api().get_controller(controller_id).ok_or_invalid()?;
+        self.is_valid(creep, controller)?;
+        if creep.pos().in_range_to(controller, 3) {
+            let r = creep.upgrade_controller(controller_id);
+            if r != ReturnCode::Ok {
+                warn!("couldn't upgrade controller: {:?}", r);
+            }
+        } else {
+            creep.move_to(controller);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use crate::api::{api, Owner, StructureState};
+use crate::data::{Creep, Reservations};
+use crate::tasks::task::TaskError::Invalid;
+use crate::tasks::task::{TaskContext, TaskOption, TaskResult};
+use crate::tasks::TaskTrait;
+use screeps::{ReturnCode, StructureType};
+use serde::{Deserialize, Serialize};
+
+/// Fetches energy, by default from the closest container or storage that has some and isn't
+/// hostile.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Withdraw {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    _target_id: Option<String>,
+}
+
+impl Withdraw {
+    pub fn new(target_id: String) -> Withdraw {
+        Withdraw {
+            _target_id: Some(target_id),
+        }
+    }
+
+    fn is_valid(&self, creep: &Creep, target: &StructureState) -> TaskResult {
+        if creep.carry_total() == creep.carry_capacity() || target.stored_energy().unwrap_or(0) == 0
+        {
+            return Err(Invalid);
+        }
+        Ok(())
+    }
+}
+
+impl TaskTrait for Withdraw {
+    fn name(&self) -> &str {
+        "Withdraw"
+    }
+
+    fn start(&mut self, creep: &Creep, context: &mut TaskContext) {
+        if self._target_id.is_none() {
+            let pos = creep.pos();
+            let room = context.rooms.get(creep.room_id());
+            let reservations = &mut context.reservations;
+            let _target = room
+                .into_iter()
+                .flat_map(|room| room.structures())
+                .filter(|structure| {
+                    matches!(
+                        structure.structure_type,
+                        StructureType::Container | StructureType::Storage
+                    ) && structure.owner != Owner::Hostile
+                })
+                .filter(|structure| {
+                    let stored = structure.stored_energy().unwrap_or(0);
+                    reservations.set_capacity(&structure.id, stored);
+                    reservations.remaining(&structure.id).unwrap_or(0) > 0
+                })
+                .min_by_key(|structure| pos.get_range_to(*structure));
+            if let Some(target) = _target {
+                self._target_id = Some(target.id.clone());
+            }
+        }
+        self.reserve(creep, context.reservations);
+    }
+
+    fn execute(&mut self, creep: &Creep, _context: &mut TaskContext) -> TaskResult {
+        let target_id = &self._target_id.as_ref().ok_or_invalid()?;
+        let target = &api().get_structure(target_id).ok_or_invalid()?;
+        self.is_valid(creep, target)?;
+        if creep.pos().is_near_to(target) {
+            let r = creep.withdraw_all_energy(target_id);
+            if r != ReturnCode::Ok {
+                warn!("couldn't withdraw energy: {:?}", r);
+            }
+        } else {
+            creep.move_to(target);
+        }
+        Ok(())
+    }
+
+    fn reserve(&self, creep: &Creep, reservations: &mut Reservations) {
+        if let Some(target_id) = &self._target_id {
+            let free = creep.carry_capacity() - creep.carry_total();
+            reservations.claim_available(target_id, creep.name(), free);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use crate::api::{activate_segment, api, Owner};
+use crate::data::Room;
+use screeps::pathfinder::LocalCostMatrix;
+use screeps::{Part, Position, RoomName, StructureType, Terrain, TOWER_OPTIMAL_RANGE};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// `RawMemory` segment the structure layers are kept in, so a global reset doesn't have to
+/// build them all over again.
+pub const COST_MATRIX_SEGMENT: u32 = 0;
+/// Ticks between writes of the segment, as long as anything changed.
+const SAVE_INTERVAL: u32 = 100;
+const SEGMENT_SIZE: usize = 100 * 1024;
+
+/// Cost of a road, plains cost twice and swamps ten times as much as that.
+const ROAD_COST: u8 = 1;
+/// Cost of tiles hostiles can hit, paths only lead through them if there's no other way.
+const DANGER_COST: u8 = 100;
+pub const IMPASSABLE: u8 = 255;
+
+/// Tiles as two characters each, so a room's worth of them fits into a short string.
+fn pack(tiles: &[(u8, u8)]) -> String {
+    tiles
+        .iter()
+        .flat_map(|(x, y)| vec![char::from(b'0' + x), char::from(b'0' + y)])
+        .collect()
+}
+
+fn unpack(tiles: &str) -> Vec<(u8, u8)> {
+    tiles
+        .as_bytes()
+        .chunks(2)
+        .filter(|tile| tile.len() == 2)
+        .map(|tile| (tile[0].wrapping_sub(b'0'), tile[1].wrapping_sub(b'0')))
+        .filter(|(x, y)| *x < 50 && *y < 50)
+        .collect()
+}
+
+/// Sums up the structures of `room` that matter for moving around, whatever order the API
+/// lists them in.
+fn signature(room: &Room) -> u64 {
+    room.structures()
+        .iter()
+        .map(|structure| {
+            let mut hasher = DefaultHasher::new();
+            structure.pos.hash(&mut hasher);
+            structure.structure_type.hash(&mut hasher);
+            (structure.owner == Owner::Hostile).hash(&mut hasher);
+            hasher.finish()
+        })
+        .fold(0, u64::wrapping_add)
+}
+
+/// The part of a room's costs that only changes with its structures.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct StructureLayer {
+    #[serde(rename = "h")]
+    signature: u64,
+    #[serde(rename = "b")]
+    blocked: String,
+    #[serde(rename = "r")]
+    roads: String,
+}
+
+impl StructureLayer {
+    /// Roads are cheap, and structures in the way, except for our ramparts, along with the
+    /// objects creeps can't stand on are impassable.
+    fn from(room: &Room) -> StructureLayer {
+        let tile = |pos: Position| (pos.x() as u8, pos.y() as u8);
+        let mut blocked = vec![];
+        let mut roads = vec![];
+        for structure in room.structures() {
+            match structure.structure_type {
+                StructureType::Road => roads.push(tile(structure.pos)),
+                StructureType::Container => {}
+                StructureType::Rampart if structure.owner != Owner::Hostile => {}
+                _ => blocked.push(tile(structure.pos)),
+            }
+        }
+        blocked.extend(room.sources().iter().map(|source| tile(source.pos())));
+        blocked.extend(room.minerals().iter().map(|mineral| tile(mineral.pos)));
+        blocked.extend(room.controller().map(|controller| tile(controller.pos)));
+        StructureLayer {
+            signature: signature(room),
+            blocked: pack(&blocked),
+            roads: pack(&roads),
+        }
+    }
+
+    fn matrix(&self) -> LocalCostMatrix {
+        let mut matrix = LocalCostMatrix::new();
+        for (x, y) in unpack(&self.roads) {
+            matrix.set(x, y, ROAD_COST);
+        }
+        // roads under something in the way stay blocked
+        for (x, y) in unpack(&self.blocked) {
+            matrix.set(x, y, IMPASSABLE);
+        }
+        matrix
+    }
+}
+
+/// Cost matrices of the rooms we know, for paths to take on top of the terrain.
+#[derive(Default)]
+pub struct CostMatrices {
+    layers: HashMap<RoomName, StructureLayer>,
+    // built from `layers` the first time they're asked for
+    matrices: HashMap<RoomName, LocalCostMatrix>,
+    creeps: HashMap<RoomName, Vec<Position>>,
+    // tiles within the range of each hostile tower and creep
+    danger: HashMap<RoomName, Vec<(Position, u32)>>,
+    loaded: bool,
+    changed: bool,
+    saved_at: u32,
+}
+
+impl CostMatrices {
+    pub fn new() -> CostMatrices {
+        CostMatrices::default()
+    }
+
+    /// Rebuilds the structure layers of `rooms` whose structures changed since the last tick,
+    /// and picks up where creeps and hostiles are now.
+    pub fn refresh(&mut self, rooms: &HashMap<RoomName, Room>) {
+        if !self.loaded {
+            self.load();
+        }
+        for (name, room) in rooms.iter() {
+            let signature = signature(room);
+            if self.layers.get(name).map(|layer| layer.signature) != Some(signature) {
+                debug!("structures in {} changed, rebuilding its cost matrix", name);
+                self.layers.insert(*name, StructureLayer::from(room));
+                self.matrices.remove(name);
+                self.changed = true;
+            }
+        }
+
+        self.creeps.clear();
+        self.danger.clear();
+        for creep in api().creeps().into_iter().filter(|creep| !creep.spawning) {
+            let room = creep.pos.room_name();
+            self.creeps.entry(room).or_default().push(creep.pos);
+        }
+        for (name, room) in rooms.iter() {
+            let creeps = self.creeps.entry(*name).or_default();
+            creeps.extend(room.hostiles().iter().map(|hostile| hostile.pos));
+            let danger = self.danger.entry(*name).or_default();
+            for hostile in room.hostiles() {
+                if hostile.body.contains(&Part::RangedAttack) {
+                    danger.push((hostile.pos, 3));
+                } else if hostile.body.contains(&Part::Attack) {
+                    danger.push((hostile.pos, 1));
+                }
+            }
+            for tower in room.structures_of(StructureType::Tower) {
+                if tower.owner == Owner::Hostile {
+                    danger.push((tower.pos, TOWER_OPTIMAL_RANGE));
+                }
+            }
+        }
+    }
+
+    /// Costs of the structures in `room`, none for rooms we've never seen.
+    pub fn structures(&mut self, room: RoomName) -> &LocalCostMatrix {
+        let layers = &self.layers;
+        self.matrices.entry(room).or_insert_with(|| {
+            layers
+                .get(&room)
+                .map_or_else(LocalCostMatrix::new, StructureLayer::matrix)
+        })
+    }
+
+    /// Costs of `room` for this tick, avoiding what hostiles can hit and, if `creeps` is set,
+    /// treating creeps as impassable.
+    pub fn matrix(&mut self, room: RoomName, creeps: bool) -> LocalCostMatrix {
+        let mut matrix = self.structures(room).clone();
+        for (pos, range) in self.danger.get(&room).into_iter().flatten() {
+            let (x, y) = pos.coords_signed();
+            let range = *range as i32;
+            for tile_x in (x - range).max(0)..=(x + range).min(49) {
+                for tile_y in (y - range).max(0)..=(y + range).min(49) {
+                    let (tile_x, tile_y) = (tile_x as u8, tile_y as u8);
+                    // any cost would make walls passable
+                    let tile = Position::new(tile_x.into(), tile_y.into(), room);
+                    if matrix.get(tile_x, tile_y) < DANGER_COST
+                        && api().terrain(tile) != Terrain::Wall
+                    {
+                        matrix.set(tile_x, tile_y, DANGER_COST);
+                    }
+                }
+            }
+        }
+        if creeps {
+            for pos in self.creeps.get(&room).into_iter().flatten() {
+                matrix.set(pos.x() as u8, pos.y() as u8, IMPASSABLE);
+            }
+        }
+        matrix
+    }
+
+    /// Picks up the structure layers saved before the last global reset, once the segment is
+    /// readable from the tick after it was activated. Layers built since then are kept.
+    fn load(&mut self) {
+        activate_segment(COST_MATRIX_SEGMENT);
+        let data = match api().segment(COST_MATRIX_SEGMENT) {
+            Some(data) => data,
+            None => return,
+        };
+        self.loaded = true;
+        if data.is_empty() {
+            return;
+        }
+        match serde_json::from_str::<HashMap<RoomName, StructureLayer>>(&data) {
+            Ok(layers) => {
+                info!("loaded cost matrices of {} rooms", layers.len());
+                for (name, layer) in layers {
+                    if let Entry::Vacant(entry) = self.layers.entry(name) {
+                        entry.insert(layer);
+                        self.matrices.remove(&name);
+                    }
+                }
+            }
+            Err(error) => warn!("could not read saved cost matrices: {:?}", error),
+        }
+    }
+
+    /// Writes the structure layers into their segment, at most every [`SAVE_INTERVAL`] ticks
+    /// and only if any of them changed. Nothing is written before the saved layers were loaded.
+    pub fn save(&mut self) {
+        let time = api().time();
+        if !self.loaded || !self.changed || time < self.saved_at + SAVE_INTERVAL {
+            return;
+        }
+        let data = match serde_json::to_string(&self.layers) {
+            Ok(data) => data,
+            Err(error) => {
+                error!("could not serialize cost matrices: {:?}", error);
+                return;
+            }
+        };
+        if data.len() > SEGMENT_SIZE {
+            warn!(
+                "cost matrices take up {} bytes, too many to save",
+                data.len()
+            );
+        } else {
+            api().set_segment(COST_MATRIX_SEGMENT, &data);
+        }
+        self.changed = false;
+        self.saved_at = time;
+    }
+}
+
+thread_local! {
+    static MATRICES: RefCell<CostMatrices> = RefCell::new(CostMatrices::new());
+}
+
+/// Runs `f` with the cost matrices of the current thread, which live as long as the global.
+pub fn with_matrices<R>(f: impl FnOnce(&mut CostMatrices) -> R) -> R {
+    MATRICES.with(|matrices| f(&mut matrices.borrow_mut()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{self, pos, room_name};
+    use crate::api::MockApi;
+
+    #[test]
+    fn structure_layers_are_rebuilt_on_change_and_survive_resets() {
+        let mock = MockApi::install();
+        let name = room_name();
+        {
+            let mut world = mock.world_mut();
+            world.time = 1000;
+            world.add_structure(pos(10, 10), StructureType::Road);
+            world.add_structure(pos(11, 10), StructureType::Extension);
+            world.add_structure(pos(12, 10), StructureType::Rampart);
+            world.add_structure(pos(30, 30), StructureType::Tower).owner = Owner::Hostile;
+            world.terrain.insert(pos(27, 34), Terrain::Wall);
+            world.add_creep("Worker:1", pos(20, 20), 0);
+        }
+        let mut rooms = fixtures::rooms();
+        let mut matrices = CostMatrices::new();
+        matrices.refresh(&rooms);
+
+        let structures = matrices.structures(name);
+        assert_eq!(structures.get(10, 10), ROAD_COST);
+        assert_eq!(structures.get(11, 10), IMPASSABLE);
+        assert_eq!(structures.get(12, 10), 0);
+        let matrix = matrices.matrix(name, true);
+        assert_eq!(matrix.get(20, 20), IMPASSABLE);
+        assert_eq!(matrix.get(26, 34), DANGER_COST);
+        assert_eq!(matrix.get(27, 34), 0);
+        assert_eq!(matrix.get(30, 30), IMPASSABLE);
+        assert_eq!(matrices.matrix(name, false).get(20, 20), 0);
+
+        // a new road only shows up once the room is refreshed
+        mock.world_mut()
+            .add_structure(pos(10, 11), StructureType::Road);
+        rooms.get_mut(&name).unwrap().refresh();
+        matrices.refresh(&rooms);
+        assert_eq!(matrices.structures(name).get(10, 11), ROAD_COST);
+
+        // the segment only becomes readable on the next tick, what's in it survives until then
+        matrices.save();
+        assert!(mock.world().segments.is_empty());
+        mock.world_mut().time += 1;
+        matrices.refresh(&rooms);
+        assert!(matrices.loaded);
+        matrices.save();
+
+        // the next global reads what this one saved
+        let mut reset = CostMatrices::new();
+        reset.load();
+        assert_eq!(reset.layers, matrices.layers);
+        assert_eq!(reset.structures(name).get(10, 11), ROAD_COST);
+    }
+}
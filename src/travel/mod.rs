@@ -0,0 +1,240 @@
+//! Walking creeps along paths that are searched once and then followed step by step, instead
+//! of having the game search a new one every few ticks.
+//!
+//! Paths are kept on the heap per creep and persisted into its memory as a direction string,
+//! see [`Path`]. They are searched again when the destination changes, when the creep got
+//! pushed off its path, and when it stood still for [`STUCK_TICKS`] ticks without being
+//! fatigued, in which case other creeps count as obstacles. The costs paths are searched with
+//! come from [`CostMatrices`].
+//!
+//! Creeps only ask for their next step while their tasks run. The steps are made at the end
+//! of the tick by [`resolve`], which gets creeps standing in the way to make room.
+
+mod matrix;
+mod path;
+mod traffic;
+
+pub use self::matrix::{with_matrices, CostMatrices, COST_MATRIX_SEGMENT, IMPASSABLE};
+pub use self::path::{direction_to, offset, Path, DIRECTIONS};
+pub use self::traffic::resolve;
+
+use crate::api::api;
+use crate::data::Creep;
+use screeps::{Position, ReturnCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Ticks a creep may fail to move before it looks for a way around whatever is in its way.
+pub const STUCK_TICKS: u32 = 2;
+
+/// Where a creep is going and how, kept from one tick to the next.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TravelState {
+    #[serde(rename = "d")]
+    dest: Position,
+    #[serde(rename = "r")]
+    range: u32,
+    #[serde(rename = "p")]
+    path: Path,
+    /// Where the creep stood when it was last told to move.
+    #[serde(rename = "l", default, skip_serializing_if = "Option::is_none")]
+    last: Option<Position>,
+    #[serde(rename = "s", default, skip_serializing_if = "is_zero")]
+    stuck: u32,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+impl TravelState {
+    pub fn dest(&self) -> Position {
+        self.dest
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Ticks the creep has been failing to move.
+    pub fn stuck(&self) -> u32 {
+        self.stuck
+    }
+
+    /// Takes what happened since the last move into account: pops the step the creep took,
+    /// or counts up how long it's been stuck. Returns false if it ended up off its path.
+    fn update(&mut self, pos: Position, fatigue: u32) -> bool {
+        let last = match self.last {
+            Some(last) => last,
+            None => return true,
+        };
+        if last == pos {
+            // at the end of its path the creep is where it's meant to be
+            if fatigue == 0 && !self.path.is_empty() {
+                self.stuck += 1;
+            }
+            return true;
+        }
+        self.stuck = 0;
+        match self.path.first() {
+            Some(direction) if last + offset(direction) == pos => {
+                self.path.advance();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn search(from: Position, target: Position, range: u32, creeps: bool) -> Path {
+    let mut matrices = HashMap::new();
+    for room in [from.room_name(), target.room_name()].iter() {
+        matrices
+            .entry(*room)
+            .or_insert_with(|| with_matrices(|cached| cached.matrix(*room, creeps)));
+    }
+    let positions = api().find_path(from, target, range, &matrices);
+    Path::from_positions(from, &positions)
+}
+
+/// Asks for `creep` to take a step closer to being within `range` of `target`, along the path
+/// in `state` if it still leads there.
+pub fn travel(
+    creep: &Creep,
+    state: &mut Option<TravelState>,
+    target: Position,
+    range: u32,
+) -> ReturnCode {
+    let pos = *creep.pos();
+    if pos.get_range_to(&target) <= range {
+        *state = None;
+        return ReturnCode::Ok;
+    }
+
+    let mut current = state
+        .take()
+        .filter(|current| current.dest == target && current.range == range)
+        .and_then(|mut current| {
+            if current.update(pos, creep.fatigue()) {
+                Some(current)
+            } else {
+                None
+            }
+        });
+    let stuck = current
+        .as_ref()
+        .is_some_and(|current| current.stuck >= STUCK_TICKS);
+    if current.is_none() || stuck {
+        if stuck {
+            debug!(
+                "{} is stuck at {}, searching a way around",
+                creep.name(),
+                pos
+            );
+        }
+        current = Some(TravelState {
+            dest: target,
+            range,
+            path: search(pos, target, range, stuck),
+            last: None,
+            stuck: 0,
+        });
+    }
+
+    *state = current;
+    let current = state.as_mut().unwrap();
+    let direction = match current.path.first() {
+        Some(direction) => direction,
+        // the path ends next to targets that can't be stood on, it stays done until the
+        // creep is moved off its end
+        None if current.last.is_some() => {
+            current.last = Some(pos);
+            return ReturnCode::Ok;
+        }
+        None => return ReturnCode::NoPath,
+    };
+    current.last = Some(pos);
+    creep.request_move(direction);
+    if creep.fatigue() > 0 {
+        ReturnCode::Tired
+    } else {
+        ReturnCode::Ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::{self, pos};
+    use crate::api::MockApi;
+    use screeps::Terrain;
+
+    #[test]
+    fn paths_are_reused_until_the_creep_gets_stuck() {
+        let mock = MockApi::install();
+        {
+            let mut world = mock.world_mut();
+            // a wall across the direct way, leaving gaps at both ends
+            for y in 11..=20 {
+                world.terrain.insert(pos(12, y), Terrain::Wall);
+            }
+            world.add_creep("Scout", pos(10, 15), 0);
+        }
+        let scout = || fixtures::creep("Scout");
+        let target = pos(14, 15);
+        let mut state = None;
+
+        let creep = scout();
+        assert_eq!(travel(&creep, &mut state, target, 0), ReturnCode::Ok);
+        let mut path = state.as_ref().unwrap().path().clone();
+        assert_eq!(path.end(pos(10, 15)), target);
+        assert_eq!(creep.next_move(), path.first());
+
+        // following the path only pops the step taken
+        let next = pos(10, 15) + offset(path.first().unwrap());
+        mock.world_mut().creeps.get_mut("Scout").unwrap().pos = next;
+        travel(&scout(), &mut state, target, 0);
+        path.advance();
+        assert_eq!(state.as_ref().unwrap().path(), &path);
+
+        // another creep parks on the next tile and stays there
+        let blocked = path.first().unwrap();
+        mock.world_mut()
+            .add_creep("Blocker", next + offset(blocked), 0);
+        with_matrices(|matrices| matrices.refresh(&fixtures::rooms()));
+        travel(&scout(), &mut state, target, 0);
+        assert_eq!(state.as_ref().unwrap().stuck(), 1);
+        let creep = scout();
+        travel(&creep, &mut state, target, 0);
+        let detour = state.as_ref().unwrap().path();
+        assert_ne!(detour.first(), Some(blocked));
+        assert_eq!(detour.end(next), target);
+        assert_eq!(creep.next_move(), detour.first());
+    }
+
+    #[test]
+    fn paths_ending_next_to_a_blocked_target_stay_done() {
+        let mock = MockApi::install();
+        {
+            let mut world = mock.world_mut();
+            world.terrain.insert(pos(12, 15), Terrain::Wall);
+            world.add_creep("Scout", pos(10, 15), 0);
+        }
+        let target = pos(12, 15);
+        let mut state = None;
+        assert_eq!(
+            travel(&fixtures::creep("Scout"), &mut state, target, 0),
+            ReturnCode::Ok
+        );
+
+        // one step and it's next to the wall, as close as it gets
+        let step = state.as_ref().unwrap().path().first().unwrap();
+        mock.world_mut().creeps.get_mut("Scout").unwrap().pos = pos(10, 15) + offset(step);
+        for _ in 0..4 {
+            let creep = fixtures::creep("Scout");
+            assert_eq!(travel(&creep, &mut state, target, 0), ReturnCode::Ok);
+            assert_eq!(creep.next_move(), None);
+            assert_eq!(state.as_ref().unwrap().stuck(), 0);
+        }
+    }
+}
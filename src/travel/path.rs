@@ -0,0 +1,124 @@
+use screeps::{Direction, Position};
+use serde::{Deserialize, Serialize};
+
+/// All directions, in the order of their numbers.
+pub const DIRECTIONS: [Direction; 8] = [
+    Direction::Top,
+    Direction::TopRight,
+    Direction::Right,
+    Direction::BottomRight,
+    Direction::Bottom,
+    Direction::BottomLeft,
+    Direction::Left,
+    Direction::TopLeft,
+];
+
+/// `(dx, dy)` of one step in `direction`.
+pub fn offset(direction: Direction) -> (i32, i32) {
+    match direction {
+        Direction::Top => (0, -1),
+        Direction::TopRight => (1, -1),
+        Direction::Right => (1, 0),
+        Direction::BottomRight => (1, 1),
+        Direction::Bottom => (0, 1),
+        Direction::BottomLeft => (-1, 1),
+        Direction::Left => (-1, 0),
+        Direction::TopLeft => (-1, -1),
+    }
+}
+
+/// The direction of the step from `from` to `to`, across room borders as well.
+pub fn direction_to(from: Position, to: Position) -> Option<Direction> {
+    let step = to - from;
+    DIRECTIONS
+        .iter()
+        .cloned()
+        .find(|direction| offset(*direction) == step)
+}
+
+/// Steps to take one after the other, stored as a string of direction numbers like `"3345"`
+/// so they take up little room in memory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(transparent)]
+pub struct Path {
+    steps: String,
+}
+
+impl Path {
+    /// The steps along `positions`, starting out from `from`, up to the first gap.
+    pub fn from_positions(from: Position, positions: &[Position]) -> Path {
+        let mut steps = String::with_capacity(positions.len());
+        let mut pos = from;
+        for next in positions {
+            match direction_to(pos, *next) {
+                Some(direction) => steps.push(char::from(b'0' + direction as u8)),
+                None => break,
+            }
+            pos = *next;
+        }
+        Path { steps }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.steps
+    }
+
+    pub fn first(&self) -> Option<Direction> {
+        let digit = self.steps.bytes().next()?;
+        DIRECTIONS
+            .get((digit as usize).checked_sub(b'1' as usize)?)
+            .cloned()
+    }
+
+    /// Drops the first step, once the creep took it.
+    pub fn advance(&mut self) {
+        if !self.steps.is_empty() {
+            self.steps.remove(0);
+        }
+    }
+
+    /// Where the path leads when walked from `from`.
+    pub fn end(&self, from: Position) -> Position {
+        let mut path = self.clone();
+        let mut pos = from;
+        while let Some(direction) = path.first() {
+            pos = pos + offset(direction);
+            path.advance();
+        }
+        pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::pos;
+
+    #[test]
+    fn paths_round_trip_through_direction_strings() {
+        let from = pos(10, 10);
+        let positions = [pos(11, 10), pos(12, 11), pos(12, 12), pos(11, 13)];
+        let mut path = Path::from_positions(from, &positions);
+        assert_eq!(path.as_str(), "3456");
+        assert_eq!(path.end(from), pos(11, 13));
+        assert_eq!(serde_json::to_string(&path).unwrap(), "\"3456\"");
+
+        assert_eq!(path.first(), Some(Direction::Right));
+        path.advance();
+        assert_eq!(path.first(), Some(Direction::BottomRight));
+        assert_eq!(path.len(), 3);
+
+        // stepping over the room edge
+        let edge = pos(0, 20);
+        let west = Position::new(49, 20, "W2N1".parse().unwrap());
+        assert_eq!(direction_to(edge, west), Some(Direction::Left));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::data::Creep;
+use crate::travel::{direction_to, offset, DIRECTIONS};
+use screeps::{Direction, Position};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+fn can_move(creep: &Creep) -> bool {
+    !creep.spawning() && creep.fatigue() == 0
+}
+
+// stepping onto an exit tile takes a creep into the next room
+fn on_edge(pos: Position) -> bool {
+    let (x, y) = pos.coords();
+    x == 0 || y == 0 || x == 49 || y == 49
+}
+
+/// Works out the moves to make this tick from the steps `creeps` asked for, shoving idle creeps
+/// in the way onto tiles `walkable` holds for.
+pub fn resolve<F>(creeps: &[&Creep], mut walkable: F) -> Vec<(String, Direction)>
+where
+    F: FnMut(Position) -> bool,
+{
+    let mut creeps = creeps.to_vec();
+    // the same creeps get their way every tick, so there's no back and forth
+    creeps.sort_by(|a, b| a.name().cmp(b.name()));
+    let occupants: HashMap<Position, &Creep> = creeps
+        .iter()
+        .filter(|creep| !creep.spawning())
+        .map(|creep| (*creep.pos(), *creep))
+        .collect();
+    let mut moves: BTreeMap<&str, Direction> = creeps
+        .iter()
+        .filter(|creep| can_move(creep))
+        .filter_map(|creep| Some((creep.name(), creep.next_move()?)))
+        .collect();
+    let mut claimed: HashSet<Position> = creeps
+        .iter()
+        .filter_map(|creep| Some(*creep.pos() + offset(*moves.get(creep.name())?)))
+        .collect();
+
+    for creep in creeps.iter() {
+        let direction = match moves.get(creep.name()) {
+            Some(direction) => *direction,
+            None => continue,
+        };
+        let target = *creep.pos() + offset(direction);
+        let other = match occupants.get(&target) {
+            Some(other) => other,
+            None => continue,
+        };
+        // moving on, or moving into our tile
+        if moves.contains_key(other.name()) {
+            continue;
+        }
+        if other.is_pinned() || !can_move(other) {
+            moves.remove(creep.name());
+            continue;
+        }
+
+        let shove = DIRECTIONS.iter().cloned().find(|direction| {
+            let tile = target + offset(*direction);
+            !on_edge(tile)
+                && !occupants.contains_key(&tile)
+                && !claimed.contains(&tile)
+                && walkable(tile)
+        });
+        let direction = shove
+            .or_else(|| direction_to(target, *creep.pos()))
+            .unwrap();
+        claimed.insert(target + offset(direction));
+        moves.insert(other.name(), direction);
+    }
+
+    moves
+        .into_iter()
+        .map(|(name, direction)| (name.to_string(), direction))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::fixtures::pos;
+    use crate::api::{api, MockApi};
+    use crate::data::Roles;
+
+    #[test]
+    fn idle_creeps_make_room_unless_they_are_pinned() {
+        let mock = MockApi::install();
+        {
+            let mut world = mock.world_mut();
+            world.add_creep("Hauler:1", pos(10, 10), 0);
+            world.add_creep("Worker:1", pos(11, 10), 0);
+            world.add_creep("Hauler:2", pos(20, 10), 0);
+            world.add_creep("Hauler:3", pos(21, 10), 0);
+            world.add_creep("Miner:1", pos(30, 10), 0);
+            world.add_creep("Hauler:4", pos(31, 10), 0);
+        }
+        let roles = Roles::new();
+        let creeps: HashMap<String, Creep> = api()
+            .creeps()
+            .into_iter()
+            .map(|state| (state.name.clone(), Creep::from(state, &roles)))
+            .collect();
+        // an idle worker in the way, two haulers passing each other and a parked miner
+        creeps["Hauler:1"].request_move(Direction::Right);
+        creeps["Hauler:2"].request_move(Direction::Right);
+        creeps["Hauler:3"].request_move(Direction::Left);
+        creeps["Hauler:4"].request_move(Direction::Left);
+        creeps["Miner:1"].pin();
+
+        // only the column right of the worker is free
+        let free = |pos: Position| pos.x() == 12;
+        let moves = resolve(&creeps.values().collect::<Vec<_>>(), free);
+        assert_eq!(
+            moves,
+            vec![
+                ("Hauler:1".to_string(), Direction::Right),
+                ("Hauler:2".to_string(), Direction::Right),
+                ("Hauler:3".to_string(), Direction::Left),
+                ("Worker:1".to_string(), Direction::TopRight),
+            ]
+        );
+
+        // with nowhere to go, the worker swaps with the hauler
+        let moves = resolve(&creeps.values().collect::<Vec<_>>(), |_| false);
+        assert!(moves.contains(&("Worker:1".to_string(), Direction::Left)));
+    }
+}